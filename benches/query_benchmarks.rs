@@ -205,7 +205,7 @@ fn benchmark_query_options(c: &mut Criterion) {
     
     group.bench_function("mutation_options_builder", |b| {
         b.iter(|| {
-            let options = MutationOptions::default()
+            let options: MutationOptions = MutationOptions::default()
                 .with_retry(RetryConfig::new(2, Duration::from_millis(100)));
             black_box(options);
         });
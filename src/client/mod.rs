@@ -2,13 +2,25 @@
 //!
 //! The main client for managing query state, caching, and background updates.
 
-use crate::types::{QueryKey, QueryMeta, QueryStatus, QueryObserverId, QueryKeyPattern};
-use crate::retry::QueryError;
-use crate::infinite::{InfiniteQueryOptions, Page};
+use crate::types::{CacheEntryKind, QueryKey, QueryMeta, QueryStatus, QueryObserverId, QueryKeyPattern};
+use crate::retry::{QueryError, execute_with_retry};
+use crate::infinite::{InfiniteData, InfiniteQueryOptions, Page};
+use crate::scheduler::{FetchPriority, FetchScheduler};
+use crate::query::{QueryDefaults, QueryOptions, QuerySources};
+use crate::devtools::DevToolsManager;
+use crate::optimistic::{OptimisticConfig, OptimisticManager};
+use crate::mutation::{MutationCache, MutationDefaults, MutationOptions, PausedMutation, RegisteredMutation};
+use crate::persistence::{OfflineRequest, OfflineRequestType, PersistenceManager};
+use crate::sync::OnlineManager;
+use futures::future::{join_all, LocalBoxFuture};
+use futures::FutureExt;
 use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 use parking_lot::RwLock;
 
@@ -16,7 +28,7 @@ use parking_lot::RwLock;
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SerializedData {
     pub data: Vec<u8>,
-    #[serde(with = "instant_serde")]
+    #[serde(with = "crate::types::instant_serde")]
     pub timestamp: Instant,
 }
 
@@ -33,6 +45,11 @@ impl CacheEntry {
         self.meta.is_stale()
     }
     
+    /// Check if the cache entry holds the pages of an infinite query
+    pub fn is_infinite(&self) -> bool {
+        self.meta.kind == CacheEntryKind::Infinite
+    }
+    
     /// Get the cached data
     pub fn get_data<T: DeserializeOwned>(&self) -> Result<T, QueryError> {
         bincode::deserialize(&self.data.data)
@@ -40,32 +57,395 @@ impl CacheEntry {
     }
 }
 
+/// Page function of an infinite query
+type InfinitePageFn<T, TPageParam> = Arc<dyn Fn(TPageParam) -> LocalBoxFuture<'static, Result<Page<T>, QueryError>> + Send + Sync>;
+
+/// Page function and options registered for an infinite query
+struct InfiniteQuery {
+    // An `InfinitePageFn<T, TPageParam>` for the page and param types of the query
+    fetch_page: Arc<dyn Any + Send + Sync>,
+    options: InfiniteQueryOptions,
+}
+
+/// Refetch function of an active query
+pub type RefetchFn = Arc<dyn Fn() -> LocalBoxFuture<'static, ()> + Send + Sync>;
+
+/// Callback of an active query run when its cache entry is written outside a fetch
+pub type CacheUpdateFn = Arc<dyn Fn(Option<&CacheEntry>) + Send + Sync>;
+
+/// An active query that is refetched when its key is invalidated
+#[derive(Clone)]
+struct QueryObserver {
+    key: QueryKey,
+    refetch: RefetchFn,
+    on_update: CacheUpdateFn,
+}
+
+/// Client-wide default options for queries and mutations
+#[derive(Clone, Default)]
+pub struct QueryClientConfig {
+    /// Defaults for every query, below any key pattern defaults
+    pub query_defaults: QueryDefaults,
+    /// Defaults for every mutation
+    pub mutation_defaults: MutationDefaults,
+}
+
+impl QueryClientConfig {
+    /// Create a config that leaves every option alone
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the defaults for every query
+    pub fn with_query_defaults(mut self, defaults: QueryDefaults) -> Self {
+        self.query_defaults = defaults;
+        self
+    }
+    
+    /// Set the defaults for every mutation
+    pub fn with_mutation_defaults(mut self, defaults: MutationDefaults) -> Self {
+        self.mutation_defaults = defaults;
+        self
+    }
+}
+
+/// Get how specific a key pattern is; more specific defaults win
+fn pattern_specificity(pattern: &QueryKeyPattern) -> (u8, usize) {
+    match pattern {
+        QueryKeyPattern::Contains(substring) => (0, substring.len()),
+        QueryKeyPattern::Prefix(prefix) => (1, prefix.len()),
+        QueryKeyPattern::Exact(key) => (2, key.len()),
+    }
+}
+
+/// Clears the resume flag when a resume of paused mutations finishes or is dropped
+struct ResumeGuard<'a>(&'a AtomicBool);
+
+impl Drop for ResumeGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
 /// The main query client
 #[derive(Clone)]
 pub struct QueryClient {
     cache: Arc<RwLock<HashMap<QueryKey, CacheEntry>>>,
+    observers: Arc<RwLock<HashMap<QueryObserverId, QueryObserver>>>,
     stale_time: Duration,
     cache_time: Duration,
+    scheduler: FetchScheduler,
+    mutation_scopes: Arc<RwLock<HashMap<String, FetchScheduler>>>,
+    mutation_cache: MutationCache,
+    infinite_queries: Arc<RwLock<HashMap<QueryKey, InfiniteQuery>>>,
+    mutation_defaults: Arc<RwLock<HashMap<QueryKey, RegisteredMutation>>>,
+    paused_mutations: FetchScheduler,
+    // Persisted paused mutations that a running mutation will resume itself
+    claimed_paused_mutations: Arc<RwLock<HashSet<String>>>,
+    resuming_paused_mutations: Arc<AtomicBool>,
+    online_manager: OnlineManager,
+    persistence: Option<Arc<PersistenceManager>>,
+    devtools: Option<Arc<DevToolsManager>>,
+    optimistic_updates: Arc<OptimisticManager<CacheEntry>>,
+    config: Arc<QueryClientConfig>,
+    query_defaults: Arc<RwLock<Vec<(QueryKeyPattern, QueryDefaults)>>>,
 }
 
 impl QueryClient {
     /// Create a new query client
     pub fn new() -> Self {
-        Self {
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            stale_time: Duration::from_secs(0),
-            cache_time: Duration::from_secs(5 * 60), // 5 minutes
-        }
+        Self::with_config(QueryClientConfig::default())
     }
     
     /// Create a new query client with custom settings
     pub fn with_settings(stale_time: Duration, cache_time: Duration) -> Self {
+        Self::with_config(QueryClientConfig::new().with_query_defaults(
+            QueryDefaults::new().with_stale_time(stale_time).with_cache_time(cache_time),
+        ))
+    }
+    
+    /// Create a new query client with default options for its queries and mutations
+    pub fn with_config(config: QueryClientConfig) -> Self {
+        let base = QueryOptions::default();
+        let stale_time = config.query_defaults.stale_time.unwrap_or(base.stale_time);
+        let cache_time = config.query_defaults.cache_time.unwrap_or(base.cache_time);
+        
         Self {
             cache: Arc::new(RwLock::new(HashMap::new())),
+            observers: Arc::new(RwLock::new(HashMap::new())),
             stale_time,
             cache_time,
+            scheduler: FetchScheduler::default(),
+            mutation_scopes: Arc::new(RwLock::new(HashMap::new())),
+            mutation_cache: MutationCache::new(cache_time),
+            infinite_queries: Arc::new(RwLock::new(HashMap::new())),
+            mutation_defaults: Arc::new(RwLock::new(HashMap::new())),
+            paused_mutations: FetchScheduler::new(Some(1)),
+            claimed_paused_mutations: Arc::new(RwLock::new(HashSet::new())),
+            resuming_paused_mutations: Arc::new(AtomicBool::new(false)),
+            online_manager: OnlineManager::new(),
+            persistence: None,
+            devtools: None,
+            optimistic_updates: Arc::new(OptimisticManager::new(OptimisticConfig::default())),
+            config: Arc::new(config),
+            query_defaults: Arc::new(RwLock::new(Vec::new())),
         }
     }
+
+    /// Get the client-wide default options
+    pub fn config(&self) -> &QueryClientConfig {
+        &self.config
+    }
+
+    /// Set default options for queries whose key matches a pattern
+    ///
+    /// Replaces earlier defaults for the same pattern.
+    pub fn set_query_defaults(&self, pattern: QueryKeyPattern, defaults: QueryDefaults) {
+        let mut query_defaults = self.query_defaults.write();
+        query_defaults.retain(|(existing, _)| *existing != pattern);
+        query_defaults.push((pattern, defaults));
+    }
+
+    /// Get the options of a query with the defaults for its key filled in
+    ///
+    /// Client-wide defaults apply first, then matching key pattern defaults from the least
+    /// to the most specific; options the query sets itself always win.
+    pub fn resolve_query_options(&self, key: &QueryKey, options: QueryOptions) -> QueryOptions {
+        let mut matching: Vec<(QueryKeyPattern, QueryDefaults)> = self
+            .query_defaults
+            .read()
+            .iter()
+            .filter(|(pattern, _)| key.matches_pattern(pattern))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|(pattern, _)| pattern_specificity(pattern));
+        
+        let defaults = matching
+            .iter()
+            .fold(self.config.query_defaults.clone(), |defaults, (_, specific)| defaults.merge(specific));
+        defaults.apply_to(options)
+    }
+
+    /// Get the options of a mutation with the client-wide defaults filled in
+    pub fn resolve_mutation_options<TData, TError, TVariables, TContext>(
+        &self,
+        options: MutationOptions<TData, TError, TVariables, TContext>,
+    ) -> MutationOptions<TData, TError, TVariables, TContext> {
+        self.config.mutation_defaults.apply_to(options)
+    }
+
+    /// Limit the number of fetches that may run at the same time
+    pub fn with_max_concurrent_fetches(self, max: usize) -> Self {
+        self.scheduler.set_max_concurrent(Some(max));
+        self
+    }
+
+    /// Get the fetch scheduler
+    pub fn scheduler(&self) -> &FetchScheduler {
+        &self.scheduler
+    }
+
+    /// Get the cache tracking every mutation started through this client
+    pub fn mutation_cache(&self) -> &MutationCache {
+        &self.mutation_cache
+    }
+
+    /// Get the scheduler that runs the mutations of a scope one at a time
+    pub(crate) fn mutation_scope(&self, scope: &str) -> FetchScheduler {
+        self.mutation_scopes
+            .write()
+            .entry(scope.to_string())
+            .or_insert_with(|| FetchScheduler::new(Some(1)))
+            .clone()
+    }
+
+    /// Get the pending optimistic updates of every optimistic mutation, holding cache entry snapshots
+    pub(crate) fn optimistic_updates(&self) -> &OptimisticManager<CacheEntry> {
+        &self.optimistic_updates
+    }
+
+    /// Get the manager tracking whether the app is online
+    pub fn online_manager(&self) -> &OnlineManager {
+        &self.online_manager
+    }
+
+    /// Persist mutations made while offline
+    pub fn with_persistence(mut self, persistence: Arc<PersistenceManager>) -> Self {
+        self.persistence = Some(persistence);
+        self
+    }
+
+    /// Get the persistence manager paused mutations are stored in
+    pub fn persistence(&self) -> Option<&Arc<PersistenceManager>> {
+        self.persistence.as_ref()
+    }
+
+    /// Write the cache entry of a key to persistence, or remove it there if the key isn't cached
+    pub async fn persist_query(&self, key: &QueryKey) -> Result<(), QueryError> {
+        let Some(persistence) = &self.persistence else { return Ok(()) };
+        match self.get_cache_entry(key) {
+            Some(entry) => persistence.store_cache_entry(key, &entry).await,
+            None => persistence.remove_cache_entry(key).await,
+        }
+    }
+
+    /// Load the persisted entry of a key into the cache if the key isn't cached yet
+    ///
+    /// Active queries for the key see the restored data right away. Returns true if an entry was restored.
+    pub async fn restore_persisted_query(&self, key: &QueryKey) -> bool {
+        let Some(persistence) = &self.persistence else { return false };
+        if self.cache.read().contains_key(key) {
+            return false;
+        }
+        match persistence.retrieve_cache_entry(key).await {
+            Ok(Some(entry)) if !entry.meta.is_expired() => {
+                self.restore_cache_entry(key, Some(entry));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Mark the persisted copies of invalidated entries stale, so a restore refetches them
+    async fn invalidate_persisted_queries(&self, keys: &[QueryKey]) {
+        if let Some(persistence) = &self.persistence {
+            for key in keys {
+                if let Ok(Some(mut entry)) = persistence.retrieve_cache_entry(key).await {
+                    entry.meta.invalidated = true;
+                    let _ = persistence.store_cache_entry(key, &entry).await;
+                }
+            }
+        }
+    }
+
+    /// Register the function and options of a mutation key
+    ///
+    /// Paused mutations with this key are persisted and can be resumed after a reload.
+    pub fn set_mutation_defaults<TData, TError, TVariables, F, Fut>(
+        &self,
+        mutation_key: QueryKey,
+        mutation_fn: F,
+        options: MutationOptions<TData, TError, TVariables, impl Send + Sync + 'static>,
+    ) where
+        TData: Clone + Send + Sync + 'static,
+        TError: Clone + Send + Sync + From<QueryError> + 'static,
+        TVariables: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+        F: Fn(TVariables) -> Fut + Send + Sync + 'static + Clone,
+        Fut: Future<Output = Result<TData, TError>> + 'static,
+    {
+        let defaults = RegisteredMutation::new(mutation_key.clone(), mutation_fn, options);
+        self.mutation_defaults.write().insert(mutation_key, defaults);
+    }
+
+    /// Get the scheduler that holds paused mutations until the network is back
+    pub(crate) fn paused_mutations(&self) -> &FetchScheduler {
+        &self.paused_mutations
+    }
+
+    /// Persist a paused mutation if its key has defaults, returning its offline queue ID
+    pub(crate) async fn persist_paused_mutation(&self, mutation_key: &QueryKey, vars: &dyn Any) -> Option<String> {
+        let persistence = self.persistence.as_ref()?;
+        let defaults = self.mutation_defaults.read().get(mutation_key).cloned()?;
+        let paused = PausedMutation {
+            mutation_key: mutation_key.clone(),
+            variables: defaults.serialize_variables(vars)?,
+        };
+        
+        let request = OfflineRequest {
+            request_type: OfflineRequestType::Mutation,
+            data: bincode::serialize(&paused).ok()?,
+            timestamp: Instant::now(),
+            retry_count: 0,
+        };
+        let id = persistence.add_to_offline_queue(request).await.ok()?;
+        self.claimed_paused_mutations.write().insert(id.clone());
+        Some(id)
+    }
+
+    /// Get the stored variables of a paused mutation that is resuming
+    ///
+    /// The stored variables may have been rewritten while the mutation was paused,
+    /// for example to replace a temporary ID with the one the server assigned.
+    pub(crate) async fn paused_mutation_variables(&self, id: &str) -> Option<Box<dyn Any>> {
+        let persistence = self.persistence.as_ref()?;
+        let queue = persistence.offline_queue().await.unwrap_or_default();
+        let (_, request) = queue.into_iter().find(|(queued, _)| queued == id)?;
+        let paused = bincode::deserialize::<PausedMutation>(&request.data).ok()?;
+        let defaults = self.mutation_defaults.read().get(&paused.mutation_key).cloned()?;
+        defaults.deserialize_variables(&paused.variables)
+    }
+
+    /// Remove a resumed mutation from the offline queue once it settled, or keep it for a later resume
+    pub(crate) async fn settle_paused_mutation(&self, id: &str, settled: bool) {
+        if let Some(persistence) = &self.persistence {
+            if settled {
+                let _ = persistence.remove_from_offline_queue(id).await;
+            } else if let Ok(queue) = persistence.offline_queue().await {
+                if let Some((_, mut request)) = queue.into_iter().find(|(queued, _)| queued == id) {
+                    request.retry_count += 1;
+                    let _ = persistence.update_offline_request(id, &request).await;
+                }
+            }
+        }
+        self.claimed_paused_mutations.write().remove(id);
+    }
+
+    /// Run the persisted paused mutations in the order they were made
+    ///
+    /// Mutations still waiting inside this session resume on their own, and only one
+    /// resume runs at a time; calls made while one is running return straight away.
+    /// A mutation leaves the queue once it settles; if it can't reach the server it stays
+    /// queued and the resume stops, so later mutations don't overtake it.
+    /// Returns the number of mutations that settled.
+    pub async fn resume_paused_mutations(&self) -> usize {
+        let Some(persistence) = &self.persistence else { return 0 };
+        if self.resuming_paused_mutations.swap(true, Ordering::SeqCst) {
+            return 0;
+        }
+        let _resuming = ResumeGuard(&self.resuming_paused_mutations);
+        let Ok(queue) = persistence.offline_queue().await else { return 0 };
+        
+        let mut resumed = 0;
+        for (id, request) in queue {
+            if !self.online_manager.is_online() {
+                break;
+            }
+            if !matches!(request.request_type, OfflineRequestType::Mutation)
+                || !self.claimed_paused_mutations.write().insert(id.clone())
+            {
+                continue;
+            }
+            let defaults = bincode::deserialize::<PausedMutation>(&request.data).ok()
+                .and_then(|paused| Some((self.mutation_defaults.read().get(&paused.mutation_key).cloned()?, paused)));
+            let Some((defaults, paused)) = defaults else {
+                self.claimed_paused_mutations.write().remove(&id);
+                continue;
+            };
+            
+            let Some(mutation) = defaults.resume(self.clone(), &paused.variables) else {
+                self.claimed_paused_mutations.write().remove(&id);
+                continue;
+            };
+            let settled = mutation.await;
+            self.settle_paused_mutation(&id, settled).await;
+            if !settled {
+                break;
+            }
+            resumed += 1;
+        }
+        resumed
+    }
+
+    /// Report events to a DevTools manager
+    pub fn with_devtools(mut self, devtools: Arc<DevToolsManager>) -> Self {
+        self.devtools = Some(devtools);
+        self
+    }
+
+    /// Get the DevTools manager events are reported to
+    pub fn devtools(&self) -> Option<&Arc<DevToolsManager>> {
+        self.devtools.as_ref()
+    }
     
     /// Get a cache entry for a query key
     pub fn get_cache_entry(&self, key: &QueryKey) -> Option<CacheEntry> {
@@ -78,6 +458,61 @@ impl QueryClient {
         &self,
         key: &QueryKey,
         data: T,
+    ) -> Result<(), QueryError> {
+        self.set_query_data_with_source(key, data, None)
+    }
+    
+    /// Set query data in the cache, recording the source that served it
+    pub fn set_query_data_with_source<T: Serialize>(
+        &self,
+        key: &QueryKey,
+        data: T,
+        source: Option<String>,
+    ) -> Result<(), QueryError> {
+        self.insert_entry(key, data, source, CacheEntryKind::Query, self.stale_time, self.cache_time)
+    }
+    
+    /// Cache the result of a query fetch with the stale and cache times of its options
+    pub(crate) fn set_query_result<T: Serialize>(
+        &self,
+        key: &QueryKey,
+        data: T,
+        source: Option<String>,
+        options: &QueryOptions,
+    ) -> Result<(), QueryError> {
+        self.insert_entry(key, data, source, CacheEntryKind::Query, options.stale_time, options.cache_time)
+    }
+    
+    /// Set the pages of an infinite query in the cache
+    pub fn set_infinite_data<T: Serialize, TPageParam: Serialize>(
+        &self,
+        key: &QueryKey,
+        data: &InfiniteData<T, TPageParam>,
+    ) -> Result<(), QueryError> {
+        self.insert_entry(key, data, None, CacheEntryKind::Infinite, self.stale_time, self.cache_time)
+    }
+    
+    /// Get the cached pages of an infinite query
+    pub fn get_infinite_data<T: DeserializeOwned, TPageParam: DeserializeOwned>(
+        &self,
+        key: &QueryKey,
+    ) -> Option<InfiniteData<T, TPageParam>> {
+        let cache = self.cache.read();
+        cache
+            .get(key)
+            .filter(|entry| entry.is_infinite())
+            .and_then(|entry| entry.get_data().ok())
+    }
+    
+    /// Serialize data into a cache entry and tell active queries about it
+    fn insert_entry<T: Serialize>(
+        &self,
+        key: &QueryKey,
+        data: T,
+        source: Option<String>,
+        kind: CacheEntryKind,
+        stale_time: Duration,
+        cache_time: Duration,
     ) -> Result<(), QueryError> {
         let serialized = bincode::serialize(&data)
             .map_err(|e| QueryError::SerializationError(e.to_string()))?;
@@ -90,16 +525,64 @@ impl QueryClient {
             meta: QueryMeta {
                 status: QueryStatus::Success,
                 updated_at: Instant::now(),
-                stale_time: self.stale_time,
-                cache_time: self.cache_time,
+                stale_time,
+                cache_time,
+                error: None,
+                source,
+                kind,
+                invalidated: false,
             },
         };
         
-        let mut cache = self.cache.write();
-        cache.insert(key.clone(), entry);
+        self.cache.write().insert(key.clone(), entry.clone());
+        self.notify_observers(key, Some(&entry));
         
         Ok(())
     }
+    
+    /// Put back a previously taken cache entry, or remove the key if there was none
+    ///
+    /// Active queries for the key see the restored data right away.
+    pub fn restore_cache_entry(&self, key: &QueryKey, entry: Option<CacheEntry>) {
+        {
+            let mut cache = self.cache.write();
+            match &entry {
+                Some(entry) => cache.insert(key.clone(), entry.clone()),
+                None => cache.remove(key),
+            };
+        }
+        self.notify_observers(key, entry.as_ref());
+    }
+    
+    /// Tell active queries for a key that its cache entry changed
+    fn notify_observers(&self, key: &QueryKey, entry: Option<&CacheEntry>) {
+        let callbacks: Vec<CacheUpdateFn> = {
+            let observers = self.observers.read();
+            observers
+                .values()
+                .filter(|observer| observer.key == *key)
+                .map(|observer| observer.on_update.clone())
+                .collect()
+        };
+        
+        for on_update in callbacks {
+            on_update(entry);
+        }
+    }
+
+    /// Record a failed refetch on a cached query without discarding its data
+    ///
+    /// Returns false if there is no cache entry for the key.
+    pub fn set_query_error(&self, key: &QueryKey, error: QueryError) -> bool {
+        let mut cache = self.cache.write();
+        match cache.get_mut(key) {
+            Some(entry) => {
+                entry.meta.error = Some(error);
+                true
+            }
+            None => false,
+        }
+    }
 
     /// Get query data from the cache
     pub fn get_query_data<T: DeserializeOwned>(&self, key: &QueryKey) -> Option<T> {
@@ -111,12 +594,112 @@ impl QueryClient {
         }
     }
     
+    /// Update the data of every cached query matching a pattern
+    ///
+    /// Infinite queries and queries whose data can't be read as a `T` are skipped, and returning
+    /// `None` leaves a query unchanged. Updated queries keep their stale and cache times.
+    /// Returns the number of queries that were updated.
+    pub fn set_queries_data<T: Serialize + DeserializeOwned>(
+        &self,
+        pattern: &QueryKeyPattern,
+        updater: impl Fn(T) -> Option<T>,
+    ) -> usize {
+        let entries: Vec<(QueryKey, CacheEntry)> = {
+            let cache = self.cache.read();
+            cache
+                .iter()
+                .filter(|(key, entry)| entry.meta.kind == CacheEntryKind::Query && key.matches_pattern(pattern))
+                .map(|(key, entry)| (key.clone(), entry.clone()))
+                .collect()
+        };
+        
+        let mut updated = 0;
+        for (key, entry) in entries {
+            let Ok(old_data) = entry.get_data::<T>() else { continue };
+            let Some(new_data) = updater(old_data) else { continue };
+            let meta = entry.meta;
+            if self.insert_entry(&key, new_data, meta.source, CacheEntryKind::Query, meta.stale_time, meta.cache_time).is_ok() {
+                updated += 1;
+            }
+        }
+        updated
+    }
+    
+    /// Fetch a query, returning cached data while it is fresh
+    pub async fn fetch_query<T, F, Fut>(
+        &self,
+        key: &QueryKey,
+        query_fn: F,
+        options: &QueryOptions,
+    ) -> Result<T, QueryError>
+    where
+        T: Serialize + DeserializeOwned + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, QueryError>> + 'static,
+    {
+        let options = self.resolve_query_options(key, options.clone());
+        let sources = QuerySources::new().with_source("primary", query_fn, options.retry.clone());
+        self.fetch_query_with_fallback(key, &sources, &options).await
+    }
+    
+    /// Fetch a query from the first source that succeeds, returning cached data while it is fresh
+    pub async fn fetch_query_with_fallback<T>(
+        &self,
+        key: &QueryKey,
+        sources: &QuerySources<T>,
+        options: &QueryOptions,
+    ) -> Result<T, QueryError>
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        if let Some(entry) = self.get_cache_entry(key) {
+            if !entry.is_stale() {
+                if let Ok(data) = entry.get_data::<T>() {
+                    return Ok(data);
+                }
+            }
+        }
+        
+        let options = self.resolve_query_options(key, options.clone());
+        let _permit = self.scheduler.acquire(key, options.priority).await?;
+        let (data, source) = sources.fetch(&options).await?;
+        self.set_query_result(key, &data, Some(source), &options)?;
+        Ok(data)
+    }
+    
     /// Remove a query from the cache
     pub fn remove_query(&self, key: &QueryKey) {
         let mut cache = self.cache.write();
         cache.remove(key);
     }
     
+    /// Move cached queries whose key contains a segment to the key with that segment replaced
+    ///
+    /// Returns the number of queries that were moved.
+    pub fn replace_key_segment(&self, from: &str, to: &str) -> usize {
+        let moved: Vec<(QueryKey, CacheEntry)> = {
+            let mut cache = self.cache.write();
+            let renamed: Vec<(QueryKey, QueryKey)> = cache
+                .keys()
+                .filter_map(|key| key.replace_segment(from, to).map(|new_key| (key.clone(), new_key)))
+                .collect();
+            
+            renamed
+                .into_iter()
+                .filter_map(|(old_key, new_key)| {
+                    let entry = cache.remove(&old_key)?;
+                    cache.insert(new_key.clone(), entry.clone());
+                    Some((new_key, entry))
+                })
+                .collect()
+        };
+        
+        for (key, entry) in &moved {
+            self.notify_observers(key, Some(entry));
+        }
+        moved.len()
+    }
+    
     /// Clear all queries from the cache
     pub fn clear_cache(&self) {
         let mut cache = self.cache.write();
@@ -140,17 +723,100 @@ impl QueryClient {
     }
 
     /// Invalidate queries matching a pattern
-    pub fn invalidate_queries(&self, pattern: &QueryKeyPattern) {
-        let mut cache = self.cache.write();
-        let keys_to_remove: Vec<QueryKey> = cache
-            .keys()
-            .filter(|key| key.matches_pattern(pattern))
-            .cloned()
-            .collect();
+    ///
+    /// Matching entries are marked stale but keep their data. Returns the refetches of active
+    /// queries and the update of persisted copies, which only run once the caller drives them,
+    /// for example with `spawn_local`.
+    pub fn invalidate_queries(&self, pattern: &QueryKeyPattern) -> Vec<LocalBoxFuture<'static, ()>> {
+        let invalidated = self.mark_matching_queries_invalidated(pattern);
+        let mut pending: Vec<LocalBoxFuture<'static, ()>> =
+            self.active_refetches(pattern).iter().map(|refetch| refetch()).collect();
+        if self.persistence.is_some() && !invalidated.is_empty() {
+            let client = self.clone();
+            pending.push(async move { client.invalidate_persisted_queries(&invalidated).await }.boxed_local());
+        }
+        pending
+    }
+    
+    /// Invalidate queries matching a pattern and wait until active queries have refetched
+    pub async fn invalidate_and_refetch_queries(&self, pattern: &QueryKeyPattern) {
+        let invalidated = self.mark_matching_queries_invalidated(pattern);
+        self.invalidate_persisted_queries(&invalidated).await;
         
-        for key in keys_to_remove {
-            cache.remove(&key);
+        let refetches = self.active_refetches(pattern);
+        join_all(refetches.iter().map(|refetch| refetch())).await;
+    }
+    
+    /// Mark one cached query stale without refetching active queries
+    pub(crate) fn invalidate_query(&self, key: &QueryKey) {
+        self.mark_matching_queries_invalidated(&QueryKeyPattern::Exact(key.clone()));
+    }
+    
+    /// Mark all entries matching a pattern as invalidated, returning their keys
+    fn mark_matching_queries_invalidated(&self, pattern: &QueryKeyPattern) -> Vec<QueryKey> {
+        let invalidated: Vec<(QueryKey, CacheEntry)> = {
+            let mut cache = self.cache.write();
+            cache
+                .iter_mut()
+                .filter(|(key, _)| key.matches_pattern(pattern))
+                .map(|(key, entry)| {
+                    entry.meta.invalidated = true;
+                    (key.clone(), entry.clone())
+                })
+                .collect()
+        };
+        
+        for (key, entry) in &invalidated {
+            self.notify_observers(key, Some(entry));
         }
+        invalidated.into_iter().map(|(key, _)| key).collect()
+    }
+    
+    /// Get the refetch functions of active queries matching a pattern
+    fn active_refetches(&self, pattern: &QueryKeyPattern) -> Vec<RefetchFn> {
+        let observers = self.observers.read();
+        observers
+            .values()
+            .filter(|observer| observer.key.matches_pattern(pattern))
+            .map(|observer| observer.refetch.clone())
+            .collect()
+    }
+    
+    /// Register an active query so invalidation can refetch it and cache writes reach it
+    pub fn register_observer(
+        &self,
+        key: QueryKey,
+        refetch: impl Fn() -> LocalBoxFuture<'static, ()> + Send + Sync + 'static,
+        on_update: impl Fn(Option<&CacheEntry>) + Send + Sync + 'static,
+    ) -> QueryObserverId {
+        let id = QueryObserverId::new();
+        let mut observers = self.observers.write();
+        observers.insert(id.clone(), QueryObserver {
+            key,
+            refetch: Arc::new(refetch),
+            on_update: Arc::new(on_update),
+        });
+        id
+    }
+    
+    /// Update the key an active query is observing
+    pub fn update_observer_key(&self, id: &QueryObserverId, key: QueryKey) {
+        let mut observers = self.observers.write();
+        if let Some(observer) = observers.get_mut(id) {
+            observer.key = key;
+        }
+    }
+    
+    /// Unregister an active query
+    pub fn unregister_observer(&self, id: &QueryObserverId) {
+        let mut observers = self.observers.write();
+        observers.remove(id);
+    }
+    
+    /// Check if any active query is observing a key
+    pub fn is_query_active(&self, key: &QueryKey) -> bool {
+        let observers = self.observers.read();
+        observers.values().any(|observer| observer.key == *key)
     }
     
     /// Clean up stale entries
@@ -159,27 +825,63 @@ impl QueryClient {
         cache.retain(|_, entry| !entry.is_stale());
     }
 
-    /// Infinite query support methods
-    /// Fetch a specific page for infinite queries
-    pub async fn fetch_infinite_page<T: Clone + Serialize + DeserializeOwned>(
+    /// Register the page function and options of an infinite query
+    pub fn register_infinite_query<T, TPageParam, F, Fut>(
         &self,
-        _key: &QueryKey,
-        _page: usize,
-    ) -> Result<Page<T>, QueryError> {
-        // For now, this is a placeholder that would integrate with the actual query system
-        // In a full implementation, this would trigger the query function and return the page
-        todo!("Infinite page fetching not yet implemented")
+        key: &QueryKey,
+        query_fn: F,
+        options: InfiniteQueryOptions,
+    )
+    where
+        T: 'static,
+        TPageParam: 'static,
+        F: Fn(TPageParam) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Page<T>, QueryError>> + 'static,
+    {
+        let fetch_page: InfinitePageFn<T, TPageParam> = Arc::new(move |param| query_fn(param).boxed_local());
+        let mut infinite_queries = self.infinite_queries.write();
+        infinite_queries.insert(key.clone(), InfiniteQuery {
+            fetch_page: Arc::new(fetch_page),
+            options,
+        });
     }
 
-    /// Get infinite query options for a key
-    pub fn get_infinite_options(&self, _key: &QueryKey) -> InfiniteQueryOptions {
-        InfiniteQueryOptions::default()
+    /// Fetch the page of an infinite query for a page param with its registered page function
+    ///
+    /// The fetch waits for a scheduler slot at normal priority.
+    pub async fn fetch_infinite_page<T, TPageParam>(
+        &self,
+        key: &QueryKey,
+        page_param: TPageParam,
+    ) -> Result<Page<T>, QueryError>
+    where
+        T: Clone + Serialize + DeserializeOwned + 'static,
+        TPageParam: Clone + 'static,
+    {
+        let (fetch_page, options) = {
+            let infinite_queries = self.infinite_queries.read();
+            let query = infinite_queries
+                .get(key)
+                .ok_or_else(|| QueryError::GenericError(format!("No infinite query registered for {}", key)))?;
+            let fetch_page = query
+                .fetch_page
+                .downcast_ref::<InfinitePageFn<T, TPageParam>>()
+                .cloned()
+                .ok_or_else(|| QueryError::GenericError(format!("Page type doesn't match the infinite query {}", key)))?;
+            (fetch_page, query.options.clone())
+        };
+        
+        let _permit = self.scheduler.acquire(key, FetchPriority::Normal).await?;
+        execute_with_retry(|| fetch_page(page_param.clone()), &options.retry).await
     }
 
-    /// Register an infinite query observer
-    pub fn register_infinite_observer(&self, _key: &QueryKey) -> QueryObserverId {
-        // Generate a unique observer ID
-        QueryObserverId::new()
+    /// Get infinite query options for a key
+    pub fn get_infinite_options(&self, key: &QueryKey) -> InfiniteQueryOptions {
+        let infinite_queries = self.infinite_queries.read();
+        infinite_queries
+            .get(key)
+            .map(|query| query.options.clone())
+            .unwrap_or_default()
     }
 }
 
@@ -248,31 +950,280 @@ mod tests {
         assert_eq!(stats.total_entries, 2);
         assert_eq!(stats.stale_entries, 0);
     }
-}
-
-/// Serialization helpers for Instant
-mod instant_serde {
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-
-    pub fn serialize<S>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        // Convert Instant to SystemTime for serialization
-        let system_time = SystemTime::now() - instant.elapsed();
-        let duration = system_time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
-        duration.serialize(serializer)
+    
+    #[test]
+    fn test_set_query_error_keeps_data() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("test");
+        let data = TestData { value: 7, text: "kept".to_string() };
+        
+        assert!(!client.set_query_error(&key, QueryError::NetworkError("down".to_string())));
+        
+        client.set_query_data(&key, data.clone()).unwrap();
+        assert!(client.set_query_error(&key, QueryError::NetworkError("down".to_string())));
+        
+        let entry = client.get_cache_entry(&key).unwrap();
+        assert!(entry.meta.error.is_some());
+        assert_eq!(entry.get_data::<TestData>().unwrap(), data);
+        
+        // A successful write clears the error again
+        client.set_query_data(&key, data).unwrap();
+        assert!(client.get_cache_entry(&key).unwrap().meta.error.is_none());
     }
-
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Instant, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let duration = Duration::deserialize(deserializer)?;
-        let system_time = UNIX_EPOCH + duration;
-        let now = SystemTime::now();
-        let elapsed = now.duration_since(system_time).unwrap_or(Duration::ZERO);
-        Ok(Instant::now() - elapsed)
+    
+    #[test]
+    fn test_set_queries_data_skips_infinite_queries_and_keeps_times() {
+        let client = QueryClient::new();
+        let list = QueryKey::new(["todos", "list"]);
+        let feed = QueryKey::new(["todos", "feed"]);
+        let options = QueryOptions::default().with_stale_time(Duration::from_secs(600));
+        client.set_query_result(&list, vec![1_u32, 2], Some("server".to_string()), &options).unwrap();
+        // Empty pages would also read as an empty Vec<u32>
+        let pages = InfiniteData::<u32, u32> { pages: Vec::new(), page_params: Vec::new(), dropped_before: Vec::new(), dropped_after: Vec::new() };
+        client.set_infinite_data(&feed, &pages).unwrap();
+        
+        let updated = client.set_queries_data::<Vec<u32>>(&QueryKeyPattern::Prefix(QueryKey::from("todos")), |mut ids| {
+            ids.push(3);
+            Some(ids)
+        });
+        
+        assert_eq!(updated, 1);
+        let entry = client.get_cache_entry(&list).unwrap();
+        assert_eq!(entry.get_data::<Vec<u32>>().unwrap(), vec![1, 2, 3]);
+        assert_eq!(entry.meta.stale_time, Duration::from_secs(600));
+        assert_eq!(entry.meta.source.as_deref(), Some("server"));
+        assert!(client.get_infinite_data::<u32, u32>(&feed).unwrap().pages.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_invalidate_and_refetch_active_queries() {
+        use futures::FutureExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        let client = QueryClient::new();
+        let refetches = Arc::new(AtomicUsize::new(0));
+        let users = QueryKey::new(["users", "1"]);
+        
+        let counter = refetches.clone();
+        let observer = client.register_observer(users.clone(), move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            .boxed_local()
+        }, |_| {});
+        client.set_query_data(&users, 1).unwrap();
+        assert!(client.is_query_active(&users));
+        
+        client.invalidate_and_refetch_queries(&QueryKeyPattern::Prefix(QueryKey::from("posts"))).await;
+        assert_eq!(refetches.load(Ordering::SeqCst), 0);
+        assert!(!client.get_cache_entry(&users).unwrap().meta.invalidated);
+        
+        client.invalidate_and_refetch_queries(&QueryKeyPattern::Prefix(QueryKey::from("users"))).await;
+        assert_eq!(refetches.load(Ordering::SeqCst), 1);
+        assert!(client.get_cache_entry(&users).unwrap().meta.invalidated);
+        
+        // Without awaiting, the refetches are handed back instead of spawned
+        client.set_query_data(&users, 2).unwrap();
+        let pending = client.invalidate_queries(&QueryKeyPattern::Prefix(QueryKey::from("users")));
+        assert!(client.get_cache_entry(&users).unwrap().meta.invalidated);
+        assert_eq!(refetches.load(Ordering::SeqCst), 1);
+        join_all(pending).await;
+        assert_eq!(refetches.load(Ordering::SeqCst), 2);
+        
+        client.unregister_observer(&observer);
+        assert!(!client.is_query_active(&users));
+    }
+    
+    #[tokio::test]
+    async fn test_resume_persisted_paused_mutations() {
+        use crate::mutation::MutationOptions;
+        use crate::persistence::{PersistenceConfig, PersistenceManager};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        let persistence = Arc::new(PersistenceManager::new(PersistenceConfig::default()).await.unwrap());
+        let key = QueryKey::new(["todos", "add"]);
+        let saved = Arc::new(AtomicUsize::new(0));
+        let register_defaults = |client: &QueryClient| {
+            let saved = saved.clone();
+            client.set_mutation_defaults(
+                key.clone(),
+                |todo: String| async move { Ok::<_, QueryError>(todo) },
+                MutationOptions::default().on_success(move |_: &QueryClient, _: &String, _: &String, _: Option<&()>| {
+                    saved.fetch_add(1, Ordering::SeqCst);
+                }),
+            );
+        };
+        
+        // A mutation made offline before a reload
+        let before_reload = QueryClient::new().with_persistence(persistence.clone());
+        register_defaults(&before_reload);
+        assert!(before_reload.persist_paused_mutation(&key, &"milk".to_string()).await.is_some());
+        
+        let after_reload = QueryClient::new().with_persistence(persistence.clone());
+        register_defaults(&after_reload);
+        // Mounting and coming back online may both trigger a resume
+        let (first, second) = futures::join!(after_reload.resume_paused_mutations(), after_reload.resume_paused_mutations());
+        assert_eq!(first + second, 1);
+        assert_eq!(saved.load(Ordering::SeqCst), 1);
+        assert!(persistence.offline_queue().await.unwrap().is_empty());
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_failed_resume_keeps_paused_mutations_queued() {
+        use crate::mutation::MutationOptions;
+        use crate::persistence::{PersistenceConfig, PersistenceManager};
+        use parking_lot::Mutex;
+        
+        let persistence = Arc::new(PersistenceManager::new(PersistenceConfig::default()).await.unwrap());
+        let key = QueryKey::new(["todos", "add"]);
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let register_defaults = |client: &QueryClient| {
+            let sent = sent.clone();
+            client.set_mutation_defaults(
+                key.clone(),
+                move |todo: String| {
+                    sent.lock().push(todo.clone());
+                    let reachable = sent.lock().len() > 1;
+                    async move {
+                        if reachable {
+                            Ok(todo)
+                        } else {
+                            Err(QueryError::NetworkError("down".to_string()))
+                        }
+                    }
+                },
+                MutationOptions::default(),
+            );
+        };
+        
+        let before_reload = QueryClient::new().with_persistence(persistence.clone());
+        register_defaults(&before_reload);
+        before_reload.persist_paused_mutation(&key, &"milk".to_string()).await.unwrap();
+        before_reload.persist_paused_mutation(&key, &"eggs".to_string()).await.unwrap();
+        
+        let after_reload = QueryClient::new().with_persistence(persistence.clone());
+        register_defaults(&after_reload);
+        // The first mutation can't reach the server, so it and the ones after it stay queued
+        assert_eq!(after_reload.resume_paused_mutations().await, 0);
+        let queue = persistence.offline_queue().await.unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue[0].1.retry_count, 1);
+        assert_eq!(*sent.lock(), vec!["milk".to_string()]);
+        
+        assert_eq!(after_reload.resume_paused_mutations().await, 2);
+        assert!(persistence.offline_queue().await.unwrap().is_empty());
+        assert_eq!(*sent.lock(), vec!["milk".to_string(), "milk".to_string(), "eggs".to_string()]);
+    }
+    
+    #[tokio::test]
+    async fn test_fetch_query_records_source() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("fallback");
+        let sources = QuerySources::new()
+            .with_source(
+                "primary",
+                || async { Err::<i32, _>(QueryError::NetworkError("down".to_string())) },
+                crate::retry::RetryConfig::new(0, Duration::from_millis(1)),
+            )
+            .with_source("snapshot", || async { Ok(5) }, crate::retry::RetryConfig::default());
+        
+        let data = client.fetch_query_with_fallback(&key, &sources, &QueryOptions::default()).await.unwrap();
+        assert_eq!(data, 5);
+        
+        let entry = client.get_cache_entry(&key).unwrap();
+        assert_eq!(entry.meta.source.as_deref(), Some("snapshot"));
+    }
+    
+    #[tokio::test]
+    async fn test_infinite_page_fetches_wait_for_a_slot() {
+        let client = QueryClient::new().with_max_concurrent_fetches(1);
+        let key = QueryKey::from("feed");
+        client.register_infinite_query(&key, |page: usize| async move {
+            Ok(Page { data: vec![page], info: Default::default() })
+        }, InfiniteQueryOptions::default());
+        
+        let running = client.scheduler().acquire(&QueryKey::from("other"), FetchPriority::Normal).await.unwrap();
+        let fetch = client.fetch_infinite_page::<usize, usize>(&key, 1);
+        futures::pin_mut!(fetch);
+        assert!((&mut fetch).now_or_never().is_none());
+        assert_eq!(client.scheduler().queued_count(), 1);
+        
+        drop(running);
+        assert_eq!(fetch.await.unwrap().data, vec![1]);
+        assert_eq!(client.scheduler().running_count(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_most_specific_query_defaults_win() {
+        use crate::retry::RetryConfig;
+        
+        let config = QueryClientConfig::new()
+            .with_query_defaults(QueryDefaults::new().with_stale_time(Duration::from_secs(10)))
+            .with_mutation_defaults(MutationDefaults::new().with_retry(RetryConfig::new(2, Duration::from_millis(1))));
+        let client = QueryClient::with_config(config);
+        client.set_query_defaults(
+            QueryKeyPattern::Prefix(QueryKey::from("todos")),
+            QueryDefaults::new().with_stale_time(Duration::from_secs(20)).with_retry(RetryConfig::new(1, Duration::from_millis(1))),
+        );
+        client.set_query_defaults(
+            QueryKeyPattern::Exact(QueryKey::new(["todos", "1"])),
+            QueryDefaults::new().with_stale_time(Duration::from_secs(30)),
+        );
+        
+        // The exact key beats the prefix, which beats the client-wide defaults
+        let todo = client.resolve_query_options(&QueryKey::new(["todos", "1"]), QueryOptions::default());
+        assert_eq!(todo.stale_time, Duration::from_secs(30));
+        assert_eq!(todo.retry.max_retries, 1);
+        let users = client.resolve_query_options(&QueryKey::from("users"), QueryOptions::default());
+        assert_eq!(users.stale_time, Duration::from_secs(10));
+        
+        // Options set by the query itself always win
+        let own = QueryOptions::default().with_stale_time(Duration::from_secs(5));
+        assert_eq!(client.resolve_query_options(&QueryKey::new(["todos", "1"]), own).stale_time, Duration::from_secs(5));
+        
+        // Fetched data is cached with the resolved stale time
+        client.fetch_query(&QueryKey::new(["todos", "2"]), || async { Ok(1) }, &QueryOptions::default()).await.unwrap();
+        let entry = client.get_cache_entry(&QueryKey::new(["todos", "2"])).unwrap();
+        assert_eq!(entry.meta.stale_time, Duration::from_secs(20));
+        
+        assert_eq!(client.resolve_mutation_options(MutationOptions::<()>::default()).retry.max_retries, 2);
+    }
+    
+    #[test]
+    fn test_explicit_options_survive_defaults() {
+        use crate::retry::RetryConfig;
+        
+        let config = QueryClientConfig::new()
+            .with_query_defaults(
+                QueryDefaults::new()
+                    .with_stale_time(Duration::from_secs(10))
+                    .with_retry(RetryConfig::new(5, Duration::from_millis(1)))
+                    .with_timeout(Duration::from_secs(2)),
+            )
+            .with_mutation_defaults(
+                MutationDefaults::new()
+                    .with_retry(RetryConfig::new(5, Duration::from_millis(1)))
+                    .with_timeout(Duration::from_secs(2)),
+            );
+        let client = QueryClient::with_config(config);
+        let key = QueryKey::from("users");
+        
+        // Values equal to the built-in defaults still count when set through a builder
+        let own = QueryOptions::default()
+            .with_stale_time(Duration::ZERO)
+            .with_retry(RetryConfig::default())
+            .without_timeout();
+        let resolved = client.resolve_query_options(&key, own);
+        assert_eq!(resolved.stale_time, Duration::ZERO);
+        assert_eq!(resolved.retry, RetryConfig::default());
+        assert_eq!(resolved.timeout, None);
+        
+        let resolved = client.resolve_mutation_options(
+            MutationOptions::<()>::default().with_retry(RetryConfig::default()).without_timeout(),
+        );
+        assert_eq!(resolved.retry, RetryConfig::default());
+        assert_eq!(resolved.timeout, None);
+        assert_eq!(client.resolve_mutation_options(MutationOptions::<()>::default()).timeout, Some(Duration::from_secs(2)));
+    }
+}
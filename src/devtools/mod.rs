@@ -2,6 +2,7 @@ use crate::client::{QueryClient, CacheEntry, CacheStats};
 use crate::types::QueryKey;
 use crate::persistence::PersistenceManager;
 use crate::optimistic::{OptimisticManager, OptimisticStats};
+use crate::scheduler::QueuedFetch;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -465,6 +466,11 @@ impl DevToolsManager {
         client.get_cache_entries()
     }
 
+    /// Get fetches waiting for a free slot in the client's scheduler
+    pub fn get_fetch_queue(&self, client: &QueryClient) -> Vec<QueuedFetch> {
+        client.scheduler().queued()
+    }
+
     /// Get optimistic update statistics
     pub fn get_optimistic_stats(&self, manager: &OptimisticManager<String>) -> OptimisticStats {
         manager.get_stats()
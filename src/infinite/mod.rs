@@ -1,5 +1,7 @@
 use crate::{
     client::QueryClient,
+    query::QueryOptions,
+    scheduler::FetchPriority,
     types::QueryKey,
     retry::RetryConfig,
     QueryError,
@@ -7,9 +9,15 @@ use crate::{
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 use serde::{de::DeserializeOwned, Serialize, Deserialize};
-use std::{sync::Arc, future::Future};
+use futures::FutureExt;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
 use crate::QueryObserverId;
 
+/// Key of an item, used to drop items repeated across pages
+type ItemKeyFn<T> = Arc<dyn Fn(&T) -> String + Send + Sync>;
+
 /// Configuration for infinite queries
 #[derive(Clone, Debug)]
 pub struct InfiniteQueryOptions {
@@ -23,6 +31,8 @@ pub struct InfiniteQueryOptions {
     pub refetch_on_window_focus: bool,
     /// Whether to refetch when reconnecting to the internet
     pub refetch_on_reconnect: bool,
+    /// Number of pages `fetch_newer` fetches looking for known items before starting over
+    pub max_newer_pages: usize,
 }
 
 impl Default for InfiniteQueryOptions {
@@ -33,6 +43,7 @@ impl Default for InfiniteQueryOptions {
             max_pages: Some(10),
             refetch_on_window_focus: true,
             refetch_on_reconnect: true,
+            max_newer_pages: 3,
         }
     }
 }
@@ -81,13 +92,18 @@ impl InfiniteQueryOptionsBuilder {
         self
     }
 
+    pub fn max_newer_pages(mut self, max: usize) -> Self {
+        self.options.max_newer_pages = max;
+        self
+    }
+
     pub fn build(self) -> InfiniteQueryOptions {
         self.options
     }
 }
 
 /// Page information for infinite queries
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PageInfo {
     /// Current page number
     pub page: usize,
@@ -99,6 +115,12 @@ pub struct PageInfo {
     pub has_next: bool,
     /// Whether there are previous pages
     pub has_prev: bool,
+    /// Cursor of the next page, for cursor-based APIs
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// Cursor of the previous page, for cursor-based APIs
+    #[serde(default)]
+    pub prev_cursor: Option<String>,
 }
 
 /// A single page of data
@@ -110,17 +132,133 @@ pub struct Page<T> {
     pub info: PageInfo,
 }
 
+/// Pages of an infinite query, cached under the query key as one entry
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InfiniteData<T, TPageParam = usize> {
+    /// Loaded pages, in display order
+    pub pages: Vec<Page<T>>,
+    /// Page param each page was fetched with
+    pub page_params: Vec<TPageParam>,
+    /// Params of pages trimmed from the start by `max_pages`, nearest last
+    pub dropped_before: Vec<TPageParam>,
+    /// Params of pages trimmed from the end by `max_pages`, nearest last
+    pub dropped_after: Vec<TPageParam>,
+}
+
+/// Derives a page param from a page and all loaded pages
+type PageParamFn<T, TPageParam> = Arc<dyn Fn(&Page<T>, &[Page<T>]) -> Option<TPageParam> + Send + Sync>;
+
+/// How an infinite query finds the param of each page and tells its items apart
+pub struct PageParams<T, TPageParam> {
+    /// Param of the first page
+    pub initial_page_param: TPageParam,
+    get_next_page_param: PageParamFn<T, TPageParam>,
+    get_previous_page_param: Option<PageParamFn<T, TPageParam>>,
+    item_key: Option<ItemKeyFn<T>>,
+}
+
+impl<T, TPageParam: Clone> Clone for PageParams<T, TPageParam> {
+    fn clone(&self) -> Self {
+        Self {
+            initial_page_param: self.initial_page_param.clone(),
+            get_next_page_param: self.get_next_page_param.clone(),
+            get_previous_page_param: self.get_previous_page_param.clone(),
+            item_key: self.item_key.clone(),
+        }
+    }
+}
+
+impl<T, TPageParam> PageParams<T, TPageParam> {
+    /// Start at `initial_page_param` and get the next param from the last page;
+    /// returning `None` means there is no next page
+    pub fn new(
+        initial_page_param: TPageParam,
+        get_next_page_param: impl Fn(&Page<T>, &[Page<T>]) -> Option<TPageParam> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            initial_page_param,
+            get_next_page_param: Arc::new(get_next_page_param),
+            get_previous_page_param: None,
+            item_key: None,
+        }
+    }
+
+    /// Get the previous param from the first page; without it there are no previous pages
+    pub fn with_previous_page_param(
+        mut self,
+        get_previous_page_param: impl Fn(&Page<T>, &[Page<T>]) -> Option<TPageParam> + Send + Sync + 'static,
+    ) -> Self {
+        self.get_previous_page_param = Some(Arc::new(get_previous_page_param));
+        self
+    }
+
+    /// Identify items so ones repeated across pages appear once in `flat_items`,
+    /// and `fetch_newer` can tell new items from loaded ones
+    pub fn with_item_key<K: ToString>(mut self, item_key: impl Fn(&T) -> K + Send + Sync + 'static) -> Self {
+        self.item_key = Some(Arc::new(move |item| item_key(item).to_string()));
+        self
+    }
+
+    /// Get the param of the page after the loaded pages
+    pub fn get_next_page_param(&self, pages: &[Page<T>]) -> Option<TPageParam> {
+        let last_page = pages.last()?;
+        (self.get_next_page_param)(last_page, pages)
+    }
+
+    /// Get the param of the page before the loaded pages
+    pub fn get_previous_page_param(&self, pages: &[Page<T>]) -> Option<TPageParam> {
+        let first_page = pages.first()?;
+        self.get_previous_page_param.as_ref()?(first_page, pages)
+    }
+}
+
+impl<T> PageParams<T, usize> {
+    /// Numbered pages starting at 0, following `PageInfo::has_next` and `has_prev`
+    pub fn numbered() -> Self {
+        Self::new(0, |last_page, _| last_page.info.has_next.then(|| last_page.info.page + 1))
+            .with_previous_page_param(|first_page, _| {
+                (first_page.info.has_prev && first_page.info.page > 0).then(|| first_page.info.page - 1)
+            })
+    }
+}
+
+impl<T> PageParams<T, String> {
+    /// Cursor pages starting without a cursor, following `PageInfo::next_cursor` and `prev_cursor`
+    pub fn cursor() -> Self {
+        Self::new(String::new(), |last_page, _| last_page.info.next_cursor.clone())
+            .with_previous_page_param(|first_page, _| first_page.info.prev_cursor.clone())
+    }
+}
+
+/// Concatenate the items of all pages, keeping only the first item with each key
+fn flatten_pages<T: Clone>(pages: &[Page<T>], item_key: Option<&ItemKeyFn<T>>) -> Vec<T> {
+    let items = pages.iter().flat_map(|page| page.data.iter());
+    match item_key {
+        Some(item_key) => {
+            let mut seen = HashSet::new();
+            items.filter(|item| seen.insert(item_key(item))).cloned().collect()
+        }
+        None => items.cloned().collect(),
+    }
+}
+
 /// Infinite query result with pagination support
 #[derive(Clone)]
-pub struct InfiniteQueryResult<T> {
+pub struct InfiniteQueryResult<T: 'static + Send + Sync, TPageParam = usize> {
     /// All pages of data
     pub pages: RwSignal<Vec<Page<T>>>,
-    /// Current page number
-    pub current_page: RwSignal<usize>,
+    /// Page param of each loaded page
+    pub page_params: RwSignal<Vec<TPageParam>>,
+    /// Items of all pages in order, without items repeated across pages
+    pub flat_items: Memo<Vec<T>>,
+    /// Params of pages trimmed from the start, fetched again before asking `params`
+    dropped_before: RwSignal<Vec<TPageParam>>,
+    /// Params of pages trimmed from the end, fetched again before asking `params`
+    dropped_after: RwSignal<Vec<TPageParam>>,
     /// Whether more data can be loaded
-    pub has_next: RwSignal<bool>,
+    pub has_next: Memo<bool>,
     /// Whether previous data exists
-    pub has_prev: RwSignal<bool>,
+    pub has_prev: Memo<bool>,
     /// Loading state
     pub is_loading: RwSignal<bool>,
     /// Error state
@@ -133,140 +271,424 @@ pub struct InfiniteQueryResult<T> {
     pub key: QueryKey,
     /// Observer ID
     pub observer_id: QueryObserverId,
+    /// How page params are derived
+    params: PageParams<T, TPageParam>,
+    /// Key of an item, from the page params
+    item_key: Option<ItemKeyFn<T>>,
+    /// Bumped whenever the loaded pages are replaced, so a refetch can tell they changed under it
+    generation: StoredValue<u64>,
     /// Client reference
-    client: Arc<QueryClient>,
+    client: QueryClient,
 }
 
-impl<T> InfiniteQueryResult<T>
+impl<T, TPageParam> InfiniteQueryResult<T, TPageParam>
 where
     T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    TPageParam: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
 {
+    /// Create the result for an infinite query registered with the client
+    ///
+    /// Starts from the pages cached under the key and follows cache writes and invalidation.
+    fn new(client: QueryClient, key: QueryKey, params: PageParams<T, TPageParam>) -> Self {
+        let cached = client.get_cache_entry(&key).filter(|entry| entry.is_infinite());
+        let data = client.get_infinite_data::<T, TPageParam>(&key).unwrap_or_else(|| InfiniteData {
+            pages: Vec::new(),
+            page_params: Vec::new(),
+            dropped_before: Vec::new(),
+            dropped_after: Vec::new(),
+        });
+        let pages = RwSignal::new(data.pages);
+        let page_params = RwSignal::new(data.page_params);
+        let dropped_before = RwSignal::new(data.dropped_before);
+        let dropped_after = RwSignal::new(data.dropped_after);
+        let is_stale = RwSignal::new(cached.as_ref().is_some_and(|entry| entry.is_stale()));
+
+        // Before the first page is loaded only a next page can be fetched
+        let next_params = params.clone();
+        let has_next = Memo::new(move |_| {
+            dropped_after.with(|dropped| !dropped.is_empty())
+                || pages.with(|pages: &Vec<Page<T>>| pages.is_empty() || next_params.get_next_page_param(pages).is_some())
+        });
+        let prev_params = params.clone();
+        let has_prev = Memo::new(move |_| {
+            dropped_before.with(|dropped| !dropped.is_empty())
+                || pages.with(|pages| prev_params.get_previous_page_param(pages).is_some())
+        });
+
+        // Items shifting between pages show up once, at their first position
+        let item_key = params.item_key.clone();
+        let flat_key = item_key.clone();
+        let flat_items = Memo::new_owning(move |_| (pages.with(|pages| flatten_pages(pages, flat_key.as_ref())), true));
+        let generation = StoredValue::new(0_u64);
+
+        let mut result = Self {
+            pages,
+            page_params,
+            flat_items,
+            dropped_before,
+            dropped_after,
+            has_next,
+            has_prev,
+            is_loading: RwSignal::new(false),
+            error: RwSignal::new(None),
+            is_stale,
+            is_fetching: RwSignal::new(false),
+            key: key.clone(),
+            observer_id: QueryObserverId::new(),
+            params,
+            item_key,
+            generation,
+            client: client.clone(),
+        };
+
+        // Invalidation refetches the loaded pages and cache writes replace them
+        let refetch_result = result.clone();
+        result.observer_id = client.register_observer(
+            key,
+            move || {
+                let result = refetch_result.clone();
+                async move {
+                    let _ = result.refetch().await;
+                }
+                .boxed_local()
+            },
+            move |entry| match entry {
+                Some(entry) if entry.is_infinite() => {
+                    if let Ok(data) = entry.get_data::<InfiniteData<T, TPageParam>>() {
+                        generation.update_value(|generation| *generation += 1);
+                        pages.set(data.pages);
+                        page_params.set(data.page_params);
+                        dropped_before.set(data.dropped_before);
+                        dropped_after.set(data.dropped_after);
+                        is_stale.set(entry.is_stale());
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    generation.update_value(|generation| *generation += 1);
+                    pages.set(Vec::new());
+                    page_params.set(Vec::new());
+                    dropped_before.set(Vec::new());
+                    dropped_after.set(Vec::new());
+                }
+            },
+        );
+
+        result
+    }
+
+    /// Fetch a page, recording any error
+    async fn fetch_page(&self, page_param: TPageParam) -> Result<Page<T>, QueryError> {
+        let result = self.client.fetch_infinite_page::<T, TPageParam>(&self.key, page_param).await;
+        self.error.set(result.as_ref().err().cloned());
+        result
+    }
+
+    /// Write the loaded pages to the cache and persistence
+    async fn store_pages(&self) {
+        self.generation.update_value(|generation| *generation += 1);
+        let data = InfiniteData {
+            pages: self.pages.get_untracked(),
+            page_params: self.page_params.get_untracked(),
+            dropped_before: self.dropped_before.get_untracked(),
+            dropped_after: self.dropped_after.get_untracked(),
+        };
+        if self.client.set_infinite_data(&self.key, &data).is_ok() {
+            let _ = self.client.persist_query(&self.key).await;
+        }
+    }
+
+    /// Get the number of loaded pages beyond `max_pages`
+    fn excess_pages(&self) -> usize {
+        let max_pages = self.client.get_infinite_options(&self.key).max_pages;
+        let loaded = self.pages.with_untracked(|pages| pages.len());
+        max_pages.map_or(0, |max_pages| loaded.saturating_sub(max_pages))
+    }
+
+    /// Drop pages beyond `max_pages` from the start, keeping their params
+    fn trim_start(&self) {
+        let excess = self.excess_pages();
+        if excess > 0 {
+            self.pages.update(|pages| {
+                pages.drain(..excess);
+            });
+            let trimmed = self.page_params.try_update(|params| params.drain(..excess).collect::<Vec<_>>());
+            self.dropped_before.update(|dropped| dropped.extend(trimmed.unwrap_or_default()));
+        }
+    }
+
+    /// Drop pages beyond `max_pages` from the end, keeping their params
+    fn trim_end(&self) {
+        let excess = self.excess_pages();
+        if excess > 0 {
+            let keep = self.pages.with_untracked(|pages| pages.len()) - excess;
+            self.pages.update(|pages| pages.truncate(keep));
+            let trimmed = self.page_params.try_update(|params| params.split_off(keep));
+            self.dropped_after.update(|dropped| dropped.extend(trimmed.unwrap_or_default().into_iter().rev()));
+        }
+    }
+
     /// Get the next page of data
+    ///
+    /// A page trimmed from the end is fetched again with its stored param.
     pub async fn fetch_next_page(&self) -> Result<(), QueryError> {
-        let current_page = self.current_page.get();
-        let has_next = self.has_next.get();
-        
-        if !has_next {
+        let dropped = self.dropped_after.with_untracked(|dropped| dropped.last().cloned());
+        let next_param = dropped.clone().or_else(|| {
+            self.pages.with_untracked(|pages| {
+                if pages.is_empty() {
+                    Some(self.params.initial_page_param.clone())
+                } else {
+                    self.params.get_next_page_param(pages)
+                }
+            })
+        });
+        let Some(next_param) = next_param else {
             return Ok(());
-        }
+        };
 
         // Update loading state
         self.is_loading.set(true);
         
         // Fetch next page
-        let next_page = current_page + 1;
-        let result = self
-            .client
-            .fetch_infinite_page::<T>(&self.key, next_page)
-            .await?;
-
-        // Update pages
-        let result_clone = result.clone();
-        self.pages.update(|pages| {
-            if let Some(max_pages) = self.client.get_infinite_options(&self.key).max_pages {
-                if pages.len() >= max_pages {
-                    pages.remove(0); // Remove oldest page
-                }
-            }
-            pages.push(result_clone);
-        });
+        let result = self.fetch_page(next_param.clone()).await;
+        self.is_loading.set(false);
+        let result = result?;
 
-        // Update current page and has_next
-        self.current_page.set(next_page);
-        self.has_next.set(result.info.has_next);
+        if dropped.is_some() {
+            self.dropped_after.update(|dropped| {
+                dropped.pop();
+            });
+        }
+        self.pages.update(|pages| pages.push(result));
+        self.page_params.update(|params| params.push(next_param));
+
+        // Slide the window forward
+        self.trim_start();
+        self.store_pages().await;
 
-        self.is_loading.set(false);
         Ok(())
     }
 
     /// Get the previous page of data
+    ///
+    /// A page trimmed from the start is fetched again with its stored param.
     pub async fn fetch_previous_page(&self) -> Result<(), QueryError> {
-        let current_page = self.current_page.get();
-        let has_prev = self.has_prev.get();
-        
-        if !has_prev {
+        let dropped = self.dropped_before.with_untracked(|dropped| dropped.last().cloned());
+        let prev_param = dropped
+            .clone()
+            .or_else(|| self.pages.with_untracked(|pages| self.params.get_previous_page_param(pages)));
+        let Some(prev_param) = prev_param else {
             return Ok(());
-        }
+        };
 
         // Update loading state
         self.is_loading.set(true);
         
         // Fetch previous page
-        let prev_page = current_page.saturating_sub(1);
-        let result = self
-            .client
-            .fetch_infinite_page::<T>(&self.key, prev_page)
-            .await?;
-
-        // Update pages
-        let result_clone = result.clone();
-        self.pages.update(|pages| {
-            pages.insert(0, result_clone);
-            
-            if let Some(max_pages) = self.client.get_infinite_options(&self.key).max_pages {
-                if pages.len() > max_pages {
-                    pages.pop(); // Remove newest page
+        let result = self.fetch_page(prev_param.clone()).await;
+        self.is_loading.set(false);
+        let result = result?;
+
+        if dropped.is_some() {
+            self.dropped_before.update(|dropped| {
+                dropped.pop();
+            });
+        }
+        self.pages.update(|pages| pages.insert(0, result));
+        self.page_params.update(|params| params.insert(0, prev_param));
+
+        // Slide the window back
+        self.trim_end();
+        self.store_pages().await;
+
+        Ok(())
+    }
+
+    /// Fetch the newest pages and put their new items in front of the loaded pages
+    ///
+    /// Pages are fetched from the initial page param until one holds an item of the first
+    /// loaded page, compared by `item_key`, so the loaded pages stay where they are. Without
+    /// `item_key`, or when no overlap shows up within `max_newer_pages`, the loaded pages are
+    /// replaced by the fetched ones. Pages trimmed from the start by `max_pages` are covered
+    /// by the fetched pages and forgotten. The loaded pages keep their params, which no longer
+    /// match the shifted feed; [`InfiniteQueryResult::refetch_pages`] derives fresh ones.
+    /// Returns the number of items fetched in front.
+    pub async fn fetch_newer(&self) -> Result<usize, QueryError> {
+        if self.pages.with_untracked(|pages| pages.is_empty()) {
+            self.refetch().await?;
+            return Ok(self.pages.with_untracked(|pages| pages.iter().map(|page| page.data.len()).sum()));
+        }
+
+        let known: HashSet<String> = match &self.item_key {
+            Some(item_key) => self.pages.with_untracked(|pages| pages[0].data.iter().map(|item| item_key(item)).collect()),
+            None => HashSet::new(),
+        };
+        let max_newer_pages = self.client.get_infinite_options(&self.key).max_newer_pages.max(1);
+
+        self.is_fetching.set(true);
+        let mut fetched = Vec::new();
+        let mut fetched_params = Vec::new();
+        let mut overlap = false;
+        let mut next_param = Some(self.params.initial_page_param.clone());
+        while let Some(param) = next_param.filter(|_| fetched.len() < max_newer_pages) {
+            let mut page = match self.fetch_page(param.clone()).await {
+                Ok(page) => page,
+                Err(error) => {
+                    self.is_fetching.set(false);
+                    return Err(error);
+                }
+            };
+
+            // Everything before the first known item is new
+            let first_known = self
+                .item_key
+                .as_ref()
+                .and_then(|item_key| page.data.iter().position(|item| known.contains(&item_key(item))));
+            if let Some(position) = first_known {
+                overlap = true;
+                page.data.truncate(position);
+                if !page.data.is_empty() {
+                    fetched.push(page);
+                    fetched_params.push(param);
                 }
+                break;
             }
-        });
 
-        // Update current page and has_prev
-        self.current_page.set(prev_page);
-        self.has_prev.set(result.info.has_prev);
+            fetched.push(page);
+            fetched_params.push(param);
+            next_param = self.params.get_next_page_param(&fetched);
+        }
+        self.is_fetching.set(false);
 
-        self.is_loading.set(false);
-        Ok(())
+        let new_items = fetched.iter().map(|page| page.data.len()).sum();
+        if overlap {
+            self.pages.update(|pages| {
+                pages.splice(..0, fetched);
+            });
+            self.page_params.update(|params| {
+                params.splice(..0, fetched_params);
+            });
+            // The fetched pages run from the head of the feed up to the loaded ones
+            self.dropped_before.set(Vec::new());
+        } else {
+            // Too much is new to stitch together, start over from the fetched pages
+            self.pages.set(fetched);
+            self.page_params.set(fetched_params);
+            self.dropped_before.set(Vec::new());
+            self.dropped_after.set(Vec::new());
+        }
+        self.trim_end();
+        self.store_pages().await;
+        self.is_stale.set(false);
+
+        Ok(new_items)
     }
 
-    /// Refetch all pages
+    /// Refetch all loaded pages
     pub async fn refetch(&self) -> Result<(), QueryError> {
+        self.refetch_pages(|_, _| true).await
+    }
+
+    /// Refetch the loaded pages accepted by `refetch_page`, in order
+    ///
+    /// The first page is refetched with its stored param and every later one with the param
+    /// `get_next_page_param` derives from the pages before it, since items may have shifted
+    /// between pages since they loaded, for example after `fetch_newer`. Pages after one
+    /// without a next param are dropped. The pages are only replaced once every refetch
+    /// succeeded; the others keep their data and params.
+    /// If the loaded pages change while the refetch runs, for example because the next page
+    /// was fetched, the refetched pages are outdated and dropped.
+    pub async fn refetch_pages(&self, refetch_page: impl Fn(&Page<T>, usize) -> bool) -> Result<(), QueryError> {
+        let pages = self.pages.get_untracked();
+        let page_params = self.page_params.get_untracked();
+        let generation = self.generation.get_value();
+        let unchanged = || self.generation.try_get_value() == Some(generation);
+
+        // Nothing loaded yet, start from the first page
+        if pages.is_empty() {
+            self.is_fetching.set(true);
+            let initial_param = self.params.initial_page_param.clone();
+            let result = self.fetch_page(initial_param.clone()).await;
+            self.is_fetching.set(false);
+
+            let page = result?;
+            if !unchanged() {
+                return Ok(());
+            }
+            self.pages.set(vec![page]);
+            self.page_params.set(vec![initial_param]);
+            self.dropped_before.set(Vec::new());
+            self.dropped_after.set(Vec::new());
+            self.store_pages().await;
+            self.is_stale.set(false);
+            return Ok(());
+        }
+
         self.is_fetching.set(true);
-        
-        // Clear existing pages
-        self.pages.set(Vec::new());
-        self.current_page.set(0);
-        self.has_next.set(true);
-        self.has_prev.set(false);
-        
-        // Fetch first page
-        let result = self
-            .client
-            .fetch_infinite_page::<T>(&self.key, 0)
-            .await?;
-
-        // Update state
-        let result_clone = result.clone();
-        self.pages.set(vec![result_clone]);
-        self.has_next.set(result.info.has_next);
-        self.is_stale.set(false);
+        let loaded = pages.len();
+        let mut refetched = Vec::with_capacity(loaded);
+        let mut refetched_params = Vec::with_capacity(loaded);
+        for (index, (page, param)) in pages.into_iter().zip(page_params).enumerate() {
+            if !refetch_page(&page, index) {
+                refetched.push(page);
+                refetched_params.push(param);
+                continue;
+            }
+            let param = if index == 0 {
+                param
+            } else {
+                match self.params.get_next_page_param(&refetched) {
+                    Some(param) => param,
+                    None => break,
+                }
+            };
+            match self.fetch_page(param.clone()).await {
+                Ok(page) => {
+                    refetched.push(page);
+                    refetched_params.push(param);
+                }
+                Err(error) => {
+                    self.is_fetching.set(false);
+                    return Err(error);
+                }
+            }
+        }
         self.is_fetching.set(false);
-        
+
+        if !unchanged() {
+            return Ok(());
+        }
+        if refetched.len() < loaded {
+            self.dropped_after.set(Vec::new());
+        }
+        self.pages.set(refetched);
+        self.page_params.set(refetched_params);
+        self.store_pages().await;
+        self.is_stale.set(false);
+
         Ok(())
     }
 
-    /// Invalidate and refetch
+    /// Mark the pages stale and refetch them
     pub async fn invalidate(&self) -> Result<(), QueryError> {
-        // TODO: Implement invalidation when the method is available
+        self.is_stale.set(true);
         self.refetch().await
     }
 
     /// Remove all pages from cache
     pub async fn remove(&self) -> Result<(), QueryError> {
+        self.generation.update_value(|generation| *generation += 1);
         self.client.remove_query(&self.key);
         self.pages.set(Vec::new());
-        self.current_page.set(0);
-        self.has_next.set(true);
-        self.has_prev.set(false);
+        self.page_params.set(Vec::new());
+        self.dropped_before.set(Vec::new());
+        self.dropped_after.set(Vec::new());
         Ok(())
     }
 
-    /// Get all data from all pages as a flat vector
+    /// Get all data from all pages as a flat vector, without repeated items
     pub fn get_all_data(&self) -> Vec<T> {
-        self.pages
-            .get()
-            .iter()
-            .flat_map(|page| page.data.clone())
-            .collect()
+        self.flat_items.get()
     }
 
     /// Get data from a specific page
@@ -277,74 +699,240 @@ where
             .map(|page| page.data.clone())
     }
 
-    /// Get the total number of items across all pages
+    /// Get the total number of items, as reported by the last loaded page
     pub fn get_total_count(&self) -> usize {
-        self.pages
-            .get()
-            .iter()
-            .map(|page| page.info.total)
-            .sum()
+        self.pages.with(|pages| pages.last().map_or(0, |page| page.info.total))
     }
 }
 
 /// Hook for infinite queries with pagination
-pub fn use_infinite_query<T, K, F, Fut>(
+pub fn use_infinite_query<T, TPageParam, K, F, Fut>(
     key_fn: impl Fn() -> K + 'static,
-    query_fn: impl Fn(usize) -> F + Clone + Send + Sync + 'static,
-    _options: InfiniteQueryOptions,
-) -> InfiniteQueryResult<T>
+    query_fn: impl Fn(TPageParam) -> F + Clone + Send + Sync + 'static,
+    page_params: PageParams<T, TPageParam>,
+    options: InfiniteQueryOptions,
+) -> InfiniteQueryResult<T, TPageParam>
 where
     T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    TPageParam: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
     K: Into<QueryKey>,
     F: Future<Output = Result<Page<T>, QueryError>> + Send + 'static,
 {
-    let client = use_context::<Arc<QueryClient>>()
+    let client = use_context::<QueryClient>()
         .expect("use_infinite_query must be used within QueryClientProvider");
 
-    let key = key_fn().into();
-    let observer_id = client.register_infinite_observer(&key);
+    // Later page fetches go through the client with the same page function
+    let key: QueryKey = key_fn().into();
+    client.register_infinite_query(&key, query_fn, options);
+    let result = InfiniteQueryResult::new(client.clone(), key, page_params);
 
-    // Create signals for state management
-    let pages = RwSignal::new(Vec::new());
-    let current_page = RwSignal::new(0);
-    let has_next = RwSignal::new(true);
-    let has_prev = RwSignal::new(false);
-    let is_loading = RwSignal::new(false);
-    let error = RwSignal::new(None);
-    let is_stale = RwSignal::new(false);
-    let is_fetching = RwSignal::new(false);
+    // Stop observing when the query goes away
+    {
+        let observer_id = result.observer_id.clone();
+        on_cleanup(move || client.unregister_observer(&observer_id));
+    }
 
-    // Initial fetch
+    // Pages cached or persisted by an earlier mount show right away and are only refetched when stale
+    let initial = result.clone();
     spawn_local(async move {
-        is_loading.set(true);
-        
-        match query_fn(0).await {
-            Ok(page) => {
-                let page_clone = page.clone();
-                pages.set(vec![page_clone]);
-                has_next.set(page.info.has_next);
-                is_stale.set(false);
-            }
-            Err(e) => {
-                error.set(Some(e));
+        initial.client.restore_persisted_query(&initial.key).await;
+        let has_pages = initial.pages.with_untracked(|pages| !pages.is_empty());
+        if has_pages && !initial.is_stale.get_untracked() {
+            return;
+        }
+
+        initial.is_loading.set(!has_pages);
+        let _ = initial.refetch().await;
+        initial.is_loading.set(false);
+    });
+
+    result
+}
+
+/// Result of a paginated query
+#[derive(Clone)]
+pub struct PaginatedQueryResult<T: 'static + Send + Sync> {
+    /// Current page, or the previous one while the current page loads
+    pub data: Signal<Option<Page<T>>>,
+    /// Current page number, starting at 0
+    pub page: Signal<usize>,
+    /// Go to a page
+    pub set_page: WriteSignal<usize>,
+    /// Number of pages, once a page has been loaded
+    pub total_pages: Signal<Option<usize>>,
+    /// Whether there is a page after the current one
+    pub has_next: Signal<bool>,
+    /// Whether there is a page before the current one
+    pub has_prev: Signal<bool>,
+    /// Whether `data` still holds the previous page
+    pub is_previous_data: Signal<bool>,
+    /// Whether the current page is loading
+    pub is_loading: Signal<bool>,
+    /// Error of the last fetch, if any
+    pub error: Signal<Option<QueryError>>,
+
+    // Actions
+    /// Refetch the current page
+    pub refetch: Callback<()>,
+}
+
+/// Get the cache key of one page of a paginated query
+fn page_key(key: &QueryKey, page: usize) -> QueryKey {
+    key.clone().with_segment(page.to_string())
+}
+
+/// Get the number of pages described by a page's info
+fn total_pages(info: &PageInfo) -> Option<usize> {
+    (info.per_page > 0).then(|| (info.total + info.per_page - 1) / info.per_page)
+}
+
+/// Load the page after `page` into the cache at idle priority, unless it is already fresh
+async fn prefetch_next_page<T, F, Fut>(client: QueryClient, key: QueryKey, page: usize, query_fn: F, options: QueryOptions)
+where
+    T: Serialize + DeserializeOwned + 'static,
+    F: Fn(usize) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Page<T>, QueryError>> + 'static,
+{
+    let next_page = page + 1;
+    let options = options.with_priority(FetchPriority::Idle);
+    let _ = client.fetch_query(&page_key(&key, next_page), move || query_fn(next_page), &options).await;
+}
+
+/// Hook for page-number navigation over `Page`s, keyed per page under `key_fn`
+///
+/// The previous page stays visible while the next one loads, and the page after the
+/// current one is prefetched in the background.
+pub fn use_paginated_query<T, F, Fut>(
+    key_fn: impl Fn() -> QueryKey + Send + Sync + 'static,
+    query_fn: F,
+    options: QueryOptions,
+) -> PaginatedQueryResult<T>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    F: Fn(usize) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<Page<T>, QueryError>> + 'static,
+{
+    let client = use_context::<QueryClient>()
+        .expect("use_paginated_query must be used within QueryClientProvider");
+
+    let (page, set_page) = signal(0usize);
+    let (data, set_data) = signal(None::<Page<T>>);
+    let (data_page, set_data_page) = signal(None::<usize>);
+    let (error, set_error) = signal(None::<QueryError>);
+    let (is_loading, set_loading) = signal(true);
+    let base_key = Memo::new(move |_| key_fn());
+
+    let run_fetch = {
+        let client = client.clone();
+
+        move |force: bool| {
+            let client = client.clone();
+            let query_fn = query_fn.clone();
+            let options = options.clone();
+            let current_page = page.get_untracked();
+            let key = base_key.get_untracked();
+
+            async move {
+                let current_key = page_key(&key, current_page);
+                let show = |loaded: Page<T>| {
+                    set_data.set(Some(loaded));
+                    set_data_page.set(Some(current_page));
+                    set_error.set(None);
+                    set_loading.set(false);
+                };
+                
+                // A cached page, such as a prefetched one, shows straight away and is refetched if stale
+                let cached = client.get_cache_entry(&current_key);
+                if let Some(entry) = &cached {
+                    if let Ok(loaded) = entry.get_data::<Page<T>>() {
+                        let has_next = loaded.info.has_next;
+                        show(loaded);
+                        if !force && !entry.is_stale() {
+                            if has_next {
+                                spawn_local(prefetch_next_page(client, key, current_page, query_fn, options));
+                            }
+                            return;
+                        }
+                    }
+                }
+                if force {
+                    client.invalidate_query(&current_key);
+                }
+
+                let fetch = query_fn.clone();
+                let result = client.fetch_query(&current_key, move || fetch(current_page), &options).await;
+
+                // A newer page took over while this one was loading
+                if page.get_untracked() != current_page || base_key.get_untracked() != key {
+                    return;
+                }
+                match result {
+                    Ok(loaded) => {
+                        let has_next = loaded.info.has_next;
+                        show(loaded);
+                        if has_next {
+                            spawn_local(prefetch_next_page(client, key, current_page, query_fn, options));
+                        }
+                    }
+                    Err(err) => {
+                        set_error.set(Some(err));
+                        set_loading.set(false);
+                    }
+                }
             }
         }
-        
-        is_loading.set(false);
+    };
+    let fetch = {
+        let run_fetch = run_fetch.clone();
+        move |force: bool| {
+            set_loading.set(true);
+            spawn_local(run_fetch(force));
+        }
+    };
+
+    // Let invalidation refetch the current page while it is mounted
+    let observer_id = client.register_observer(
+        page_key(&base_key.get_untracked(), 0),
+        move || run_fetch(true).boxed_local(),
+        move |entry| {
+            if let Some(Ok(loaded)) = entry.map(|entry| entry.get_data::<Page<T>>()) {
+                set_data.set(Some(loaded));
+                set_data_page.set(Some(page.get_untracked()));
+            }
+        },
+    );
+    {
+        let client = client.clone();
+        let observer_id = observer_id.clone();
+        on_cleanup(move || client.unregister_observer(&observer_id));
+    }
+
+    // Load the current page whenever the page or key changes
+    let initial_fetch = fetch.clone();
+    Effect::new(move |_| {
+        client.update_observer_key(&observer_id, page_key(&base_key.get(), page.get()));
+        initial_fetch(false);
     });
 
-    InfiniteQueryResult {
-        pages,
-        current_page,
-        has_next,
-        has_prev,
-        is_loading,
-        error,
-        is_stale,
-        is_fetching,
-        key,
-        observer_id,
-        client,
+    let total = Memo::new(move |_| data.with(|data| data.as_ref().and_then(|data| total_pages(&data.info))));
+    let is_previous_data = Memo::new(move |_| data_page.get() != Some(page.get()));
+    let has_next = Memo::new(move |_| match total.get() {
+        Some(total) => page.get() + 1 < total,
+        None => !is_previous_data.get() && data.with(|data| data.as_ref().is_some_and(|data| data.info.has_next)),
+    });
+    let has_prev = Memo::new(move |_| page.get() > 0);
+
+    PaginatedQueryResult {
+        data: data.into(),
+        page: page.into(),
+        set_page,
+        total_pages: total.into(),
+        has_next: has_next.into(),
+        has_prev: has_prev.into(),
+        is_previous_data: is_previous_data.into(),
+        is_loading: is_loading.into(),
+        error: error.into(),
+        refetch: Callback::new(move |_| fetch(true)),
     }
 }
 
@@ -388,6 +976,7 @@ mod tests {
             total: 100,
             has_next: true,
             has_prev: true,
+            ..Default::default()
         };
 
         assert_eq!(info.page, 1);
@@ -396,4 +985,450 @@ mod tests {
         assert!(info.has_next);
         assert!(info.has_prev);
     }
+
+    #[tokio::test]
+    async fn test_fetch_pages_through_client() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let client = QueryClient::new();
+        let key = QueryKey::from("items");
+        let attempts = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let options = InfiniteQueryOptions::builder()
+            .retry(RetryConfig::new(1, Duration::from_millis(1)))
+            .build();
+
+        // The first attempt of every page fails and is retried
+        client.register_infinite_query(&key, move |page: usize| {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt % 2 == 0 {
+                    return Err(QueryError::NetworkError("flaky".to_string()));
+                }
+                Ok(Page {
+                    data: vec![TestItem { id: page, name: format!("item {}", page) }],
+                    info: PageInfo { page, per_page: 1, total: 3, has_next: page < 2, has_prev: page > 0, ..Default::default() },
+                })
+            }
+        }, options);
+
+        let owner = Owner::new();
+        let result = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered())
+        });
+
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+        assert_eq!(result.get_all_data().len(), 2);
+
+        let cached = client.get_query_data::<InfiniteData<TestItem>>(&key).unwrap();
+        assert_eq!(cached.page_params, vec![0, 1]);
+        assert_eq!(cached.pages[1].data[0].id, 1);
+
+        let wrong_type = client.fetch_infinite_page::<String, usize>(&key, 0).await;
+        assert!(wrong_type.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refetch_loaded_pages() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let client = QueryClient::new();
+        let key = QueryKey::from("pages");
+        let version = std::sync::Arc::new(AtomicUsize::new(0));
+        let current = version.clone();
+
+        // Item names carry the version the page was fetched at; version 9 fails page 2
+        client.register_infinite_query(&key, move |page: usize| {
+            let version = current.load(Ordering::SeqCst);
+            async move {
+                if version == 9 && page == 2 {
+                    return Err(QueryError::NetworkError("offline".to_string()));
+                }
+                Ok(Page {
+                    data: vec![TestItem { id: page, name: format!("v{}", version) }],
+                    info: PageInfo { page, has_next: true, ..Default::default() },
+                })
+            }
+        }, InfiniteQueryOptions::builder().retry(RetryConfig::new(0, std::time::Duration::from_millis(1))).build());
+
+        let owner = Owner::new();
+        let result = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered())
+        });
+        let names = || result.pages.get_untracked().iter().map(|page| page.data[0].name.clone()).collect::<Vec<_>>();
+
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+
+        // Every loaded page is refetched, keeping the scroll position
+        version.store(1, Ordering::SeqCst);
+        result.refetch().await.unwrap();
+        assert_eq!(result.page_params.get_untracked(), vec![0, 1, 2]);
+        assert_eq!(names(), vec!["v1", "v1", "v1"]);
+
+        // Only the filtered pages are refetched
+        version.store(2, Ordering::SeqCst);
+        result.refetch_pages(|_, index| index == 1).await.unwrap();
+        assert_eq!(names(), vec!["v1", "v2", "v1"]);
+
+        // A failed page leaves all pages untouched
+        version.store(9, Ordering::SeqCst);
+        assert!(result.refetch().await.is_err());
+        assert_eq!(names(), vec!["v1", "v2", "v1"]);
+        let cached = client.get_query_data::<InfiniteData<TestItem>>(&key).unwrap();
+        assert_eq!(cached.pages[1].data[0].name, "v2");
+    }
+
+    #[tokio::test]
+    async fn test_refetch_dropped_when_pages_change() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("racing");
+        let version = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let current = version.clone();
+        client.register_infinite_query(&key, move |page: usize| {
+            let version = current.load(std::sync::atomic::Ordering::SeqCst);
+            async move {
+                tokio::task::yield_now().await;
+                Ok(Page {
+                    data: vec![TestItem { id: page, name: format!("v{}", version) }],
+                    info: PageInfo { page, has_next: true, ..Default::default() },
+                })
+            }
+        }, InfiniteQueryOptions::default());
+
+        let owner = Owner::new();
+        let result = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered())
+        });
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+
+        // The next page lands while both loaded pages are being refetched
+        version.store(1, std::sync::atomic::Ordering::SeqCst);
+        let (refetched, fetched) = futures::join!(result.refetch(), result.fetch_next_page());
+        refetched.unwrap();
+        fetched.unwrap();
+        assert_eq!(result.page_params.get_untracked(), vec![0, 1, 2]);
+        assert_eq!(result.pages.get_untracked().iter().map(|page| page.info.page).collect::<Vec<_>>(), vec![0, 1, 2]);
+        let cached = client.get_infinite_data::<TestItem, usize>(&key).unwrap();
+        assert_eq!(cached.pages.len(), cached.page_params.len());
+    }
+
+    #[tokio::test]
+    async fn test_pages_shared_through_client() {
+        use crate::persistence::{PersistenceConfig, PersistenceManager};
+        use crate::types::QueryKeyPattern;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let persistence = std::sync::Arc::new(PersistenceManager::new(PersistenceConfig::default()).await.unwrap());
+        let key = QueryKey::from("shared");
+        let version = std::sync::Arc::new(AtomicUsize::new(0));
+        let register = |client: &QueryClient| {
+            let current = version.clone();
+            client.register_infinite_query(&key, move |page: usize| {
+                let version = current.load(Ordering::SeqCst);
+                async move {
+                    Ok(Page {
+                        data: vec![TestItem { id: page, name: format!("v{}", version) }],
+                        info: PageInfo { page, has_next: true, ..Default::default() },
+                    })
+                }
+            }, InfiniteQueryOptions::default());
+        };
+
+        let client = QueryClient::new().with_persistence(persistence.clone());
+        register(&client);
+        let owner = Owner::new();
+        let first = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered())
+        });
+        first.refetch().await.unwrap();
+        first.fetch_next_page().await.unwrap();
+        assert!(client.get_cache_entry(&key).unwrap().is_infinite());
+
+        // A remount starts from the cached pages
+        let second = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered())
+        });
+        assert_eq!(second.page_params.get_untracked(), vec![0, 1]);
+
+        // Invalidation refetches every loaded page of the active queries
+        version.store(1, Ordering::SeqCst);
+        client.invalidate_and_refetch_queries(&QueryKeyPattern::Exact(key.clone())).await;
+        let names: Vec<String> = first.pages.get_untracked().iter().map(|page| page.data[0].name.clone()).collect();
+        assert_eq!(names, vec!["v1", "v1"]);
+
+        // A new session restores the persisted pages
+        let reloaded = QueryClient::new().with_persistence(persistence);
+        assert!(reloaded.restore_persisted_query(&key).await);
+        let data = reloaded.get_infinite_data::<TestItem, usize>(&key).unwrap();
+        assert_eq!(data.page_params, vec![0, 1]);
+        assert_eq!(data.pages[1].data[0].name, "v1");
+    }
+
+    #[tokio::test]
+    async fn test_max_pages_window() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("window");
+        client.register_infinite_query(&key, |page: usize| async move {
+            Ok(Page {
+                data: vec![TestItem { id: page, name: format!("item {}", page) }],
+                info: PageInfo { page, ..Default::default() },
+            })
+        }, InfiniteQueryOptions::builder().max_pages(Some(2)).build());
+
+        // Only forward params are known, so earlier pages come back from the stored params
+        let params = PageParams::new(0, |last_page: &Page<TestItem>, _| (last_page.info.page < 4).then(|| last_page.info.page + 1));
+        let owner = Owner::new();
+        let result = owner.with(|| InfiniteQueryResult::new(client.clone(), key.clone(), params));
+        let window = || result.page_params.get_untracked();
+
+        result.refetch().await.unwrap();
+        for _ in 0..3 {
+            result.fetch_next_page().await.unwrap();
+        }
+        assert_eq!(window(), vec![2, 3]);
+        owner.with(|| assert!(result.has_prev.get()));
+
+        result.fetch_previous_page().await.unwrap();
+        assert_eq!(window(), vec![1, 2]);
+        result.fetch_previous_page().await.unwrap();
+        assert_eq!(window(), vec![0, 1]);
+        owner.with(|| {
+            assert!(!result.has_prev.get());
+            assert!(result.has_next.get());
+        });
+
+        // Scrolling forward again reloads the trimmed pages before asking for new ones
+        result.fetch_next_page().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        assert_eq!(window(), vec![2, 3]);
+        result.fetch_next_page().await.unwrap();
+        assert_eq!(window(), vec![3, 4]);
+        owner.with(|| assert!(!result.has_next.get()));
+
+        let cached = client.get_infinite_data::<TestItem, usize>(&key).unwrap();
+        assert_eq!(cached.dropped_before, vec![0, 1, 2]);
+        assert!(cached.dropped_after.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_paginated_prefetch() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("admin-users");
+        let query_fn = |page: usize| async move {
+            Ok(Page {
+                data: vec![TestItem { id: page, name: format!("user {}", page) }],
+                info: PageInfo { page, per_page: 10, total: 41, has_next: page < 4, has_prev: page > 0, ..Default::default() },
+            })
+        };
+
+        assert_eq!(page_key(&key, 2), QueryKey::new(["admin-users", "2"]));
+        assert_eq!(total_pages(&query_fn(0).await.unwrap().info), Some(5));
+        assert_eq!(total_pages(&PageInfo::default()), None);
+
+        // The page after the current one lands in the cache under its own key
+        prefetch_next_page(client.clone(), key.clone(), 2, query_fn, QueryOptions::default()).await;
+        let prefetched = client.get_query_data::<Page<TestItem>>(&page_key(&key, 3)).unwrap();
+        assert_eq!(prefetched.data[0].name, "user 3");
+        assert!(client.get_cache_entry(&page_key(&key, 2)).is_none());
+    }
+
+    #[test]
+    fn test_paginated_query_shows_prefetched_page() {
+        use any_spawner::Executor;
+        use parking_lot::Mutex;
+
+        let _ = Executor::init_futures_executor();
+        let owner = Owner::new();
+        owner.set();
+        provide_context(QueryClient::new());
+        let requested = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requested.clone();
+        let result = use_paginated_query(
+            || QueryKey::from("admin-users"),
+            move |page: usize| {
+                // Pages fetched a second time never finish, so only cached data can show them
+                let repeat = recorded.lock().contains(&page);
+                recorded.lock().push(page);
+                async move {
+                    if repeat {
+                        futures::future::pending::<()>().await;
+                    }
+                    Ok(Page {
+                        data: vec![TestItem { id: page, name: format!("user {}", page) }],
+                        info: PageInfo { page, per_page: 10, total: 41, has_next: page < 4, has_prev: page > 0, ..Default::default() },
+                    })
+                }
+            },
+            QueryOptions::default(),
+        );
+        Executor::poll_local();
+        assert_eq!(*requested.lock(), vec![0, 1]);
+
+        // The prefetched page shows right away while it is refetched in the background
+        result.set_page.set(1);
+        Executor::poll_local();
+        assert_eq!(result.data.get_untracked().unwrap().data[0].name, "user 1");
+        assert!(!result.is_previous_data.get_untracked());
+        assert!(!result.is_loading.get_untracked());
+        assert_eq!(*requested.lock(), vec![0, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_flat_items_drop_repeated_items() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("feed-items");
+
+        // An insert at the head pushed item 1 onto the second page as well
+        client.register_infinite_query(&key, |page: usize| async move {
+            let ids = if page == 0 { vec![0, 1] } else { vec![1, 2] };
+            Ok(Page {
+                data: ids.into_iter().map(|id| TestItem { id, name: format!("item {}", id) }).collect(),
+                info: PageInfo { page, total: 4 + page, has_next: page == 0, ..Default::default() },
+            })
+        }, InfiniteQueryOptions::default());
+
+        let owner = Owner::new();
+        let result = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered().with_item_key(|item| item.id))
+        });
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+
+        owner.with(|| {
+            let ids: Vec<usize> = result.flat_items.get().iter().map(|item| item.id).collect();
+            assert_eq!(ids, vec![0, 1, 2]);
+            assert_eq!(result.get_all_data().len(), 3);
+            assert_eq!(result.get_total_count(), 5);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_fetch_newer_merges_until_overlap() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("live-feed");
+        let feed = std::sync::Arc::new(parking_lot::Mutex::new((0..6).rev().collect::<Vec<usize>>()));
+
+        // Newest first, two items per page
+        let source = feed.clone();
+        client.register_infinite_query(&key, move |page: usize| {
+            let ids: Vec<usize> = source.lock().iter().skip(page * 2).take(2).copied().collect();
+            async move {
+                Ok(Page {
+                    data: ids.into_iter().map(|id| TestItem { id, name: format!("post {}", id) }).collect(),
+                    info: PageInfo { page, has_next: true, ..Default::default() },
+                })
+            }
+        }, InfiniteQueryOptions::builder().max_newer_pages(2).build());
+
+        let owner = Owner::new();
+        let result = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered().with_item_key(|item| item.id))
+        });
+        let ids = || owner.with(|| result.flat_items.get().iter().map(|item| item.id).collect::<Vec<_>>());
+
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        assert_eq!(ids(), vec![5, 4, 3, 2]);
+
+        // New posts go in front and the loaded pages stay
+        feed.lock().splice(..0, [8, 7, 6]);
+        assert_eq!(result.fetch_newer().await.unwrap(), 3);
+        assert_eq!(ids(), vec![8, 7, 6, 5, 4, 3, 2]);
+        assert_eq!(result.pages.get_untracked().len(), 4);
+
+        // A refetch walks the shifted feed from the head, keeping every loaded post
+        result.refetch().await.unwrap();
+        assert_eq!(ids(), vec![8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(result.page_params.get_untracked(), vec![0, 1, 2, 3]);
+
+        // No known post within two pages starts over
+        feed.lock().splice(..0, 10..15);
+        assert_eq!(result.fetch_newer().await.unwrap(), 4);
+        assert_eq!(ids(), vec![10, 11, 12, 13]);
+        assert_eq!(result.page_params.get_untracked(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_newer_after_trimming_start() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("windowed-feed");
+        let feed = std::sync::Arc::new(parking_lot::Mutex::new((0..8).rev().collect::<Vec<usize>>()));
+
+        let source = feed.clone();
+        client.register_infinite_query(&key, move |page: usize| {
+            let ids: Vec<usize> = source.lock().iter().skip(page * 2).take(2).copied().collect();
+            async move {
+                Ok(Page {
+                    data: ids.into_iter().map(|id| TestItem { id, name: format!("post {}", id) }).collect(),
+                    info: PageInfo { page, has_next: true, ..Default::default() },
+                })
+            }
+        }, InfiniteQueryOptions::builder().max_pages(Some(2)).max_newer_pages(3).build());
+
+        let owner = Owner::new();
+        let result = owner.with(|| {
+            InfiniteQueryResult::new(client.clone(), key.clone(), PageParams::<TestItem, usize>::numbered().with_item_key(|item| item.id))
+        });
+        let ids = || owner.with(|| result.flat_items.get().iter().map(|item| item.id).collect::<Vec<_>>());
+
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        assert_eq!(ids(), vec![5, 4, 3, 2]);
+        owner.with(|| assert!(result.has_prev.get()));
+
+        // The newest pages reach down to the window, replacing the trimmed page before it
+        feed.lock().splice(..0, [9, 8]);
+        assert_eq!(result.fetch_newer().await.unwrap(), 4);
+        assert_eq!(ids(), vec![9, 8, 7, 6]);
+        owner.with(|| assert!(!result.has_prev.get()));
+        assert!(client.get_infinite_data::<TestItem, usize>(&key).unwrap().dropped_before.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_page_params() {
+        let client = QueryClient::new();
+        let key = QueryKey::from("feed");
+        let cursors = ["", "b", "c"];
+
+        // Each page links to its neighbours by cursor
+        client.register_infinite_query(&key, move |cursor: String| {
+            let index = cursors.iter().position(|c| *c == cursor).unwrap();
+            async move {
+                Ok(Page {
+                    data: vec![TestItem { id: index, name: cursor }],
+                    info: PageInfo {
+                        next_cursor: cursors.get(index + 1).map(|c| c.to_string()),
+                        prev_cursor: index.checked_sub(1).map(|i| cursors[i].to_string()),
+                        ..Default::default()
+                    },
+                })
+            }
+        }, InfiniteQueryOptions::default());
+
+        let owner = Owner::new();
+        let params = PageParams::cursor();
+        let result = owner.with(|| InfiniteQueryResult::<TestItem, String>::new(client.clone(), key.clone(), params));
+
+        result.refetch().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        result.fetch_next_page().await.unwrap();
+        owner.with(|| {
+            assert!(!result.has_next.get());
+            assert!(!result.has_prev.get());
+        });
+
+        // No further page is fetched once the callback returns None
+        result.fetch_next_page().await.unwrap();
+        let cached = client.get_query_data::<InfiniteData<TestItem, String>>(&key).unwrap();
+        assert_eq!(cached.page_params, vec!["", "b", "c"]);
+        assert_eq!(cached.pages.len(), 3);
+    }
 }
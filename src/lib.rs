@@ -74,28 +74,72 @@ pub mod persistence;
 pub mod optimistic;
 pub mod devtools;
 pub mod sync;
+pub mod scheduler;
 
 // Re-export main types and functions
-pub use client::{QueryClient, SerializedData, CacheEntry};
-pub use query::{use_query, QueryOptions, QueryResult};
-pub use mutation::{use_mutation, MutationOptions, MutationResult};
-pub use retry::{QueryError, RetryConfig, execute_with_retry};
-pub use types::{QueryKey, QueryStatus, QueryMeta, QueryKeyPattern, QueryObserverId};
-pub use infinite::{use_infinite_query, InfiniteQueryOptions, InfiniteQueryResult, Page, PageInfo};
+pub use client::{QueryClient, QueryClientConfig, SerializedData, CacheEntry};
+pub use query::{use_query, use_query_with_fallback, QueryDefaults, QueryOptions, QueryResult, QuerySource, QuerySources};
+pub use mutation::{use_mutation, use_idempotent_mutation, use_optimistic_mutation, MutationDefaults, MutationOptions, MutationResult, MutationStatus, MutationCache, MutationFilter, MutationState, use_is_mutating, use_mutation_state, replace_by_id, append_item, remove_by_id};
+pub use retry::{QueryError, RetryConfig, execute_with_retry, execute_with_retry_and_timeout};
+pub use types::{QueryKey, QueryStatus, QueryMeta, QueryKeyPattern, QueryObserverId, CacheEntryKind};
+pub use infinite::{use_infinite_query, use_paginated_query, InfiniteQueryOptions, InfiniteQueryResult, InfiniteData, Page, PageInfo, PageParams, PaginatedQueryResult};
 pub use persistence::{PersistenceManager, PersistenceConfig, StorageBackend};
 #[cfg(feature = "persistence")]
 pub use persistence::{LocalStorageBackend, IndexedDBBackend};
-pub use optimistic::{OptimisticManager, OptimisticConfig, OptimisticUpdate, OptimisticStats};
+pub use optimistic::{OptimisticManager, OptimisticConfig, OptimisticUpdate, OptimisticStats, IdReconciler, generate_temp_id, is_temp_id};
 pub use devtools::{DevToolsManager, DevToolsConfig, DevToolsServer, QueryMetrics, NetworkRequest, CacheOperation, DevToolsEvent, DevToolsExport};
-pub use sync::{SyncManager, ConflictResolutionStrategy, NetworkStatus, SyncResult};
+pub use sync::{SyncManager, ConflictResolutionStrategy, NetworkStatus, OnlineManager, SyncResult};
+pub use scheduler::{FetchScheduler, FetchPriority, QueuedFetch};
 
 /// Provide the QueryClient context to the app
 #[component]
 pub fn QueryClientProvider(
+    /// Default options for the client's queries and mutations
+    #[prop(optional)]
+    config: Option<QueryClientConfig>,
     children: Children,
 ) -> impl IntoView {
-    let client = QueryClient::new();
-    provide_context(client);
+    let client = QueryClient::with_config(config.unwrap_or_default());
+    #[cfg(target_arch = "wasm32")]
+    if let Some(window) = web_sys::window() {
+        let status = if window.navigator().on_line() { NetworkStatus::Online } else { NetworkStatus::Offline };
+        client.online_manager().set_status(status);
+    }
+    provide_context(client.clone());
+    
+    // Resume mutations persisted while offline once mounted and whenever the network comes back
+    let resume = {
+        let client = client.clone();
+        move || {
+            let client = client.clone();
+            leptos::task::spawn_local(async move {
+                client.resume_paused_mutations().await;
+            });
+        }
+    };
+    Effect::new({
+        let resume = resume.clone();
+        move |_| resume()
+    });
+    let listener_id = client.online_manager().subscribe(move |status| {
+        if status == NetworkStatus::Online {
+            resume();
+        }
+    });
+    
+    #[cfg(target_arch = "wasm32")]
+    {
+        let online = client.online_manager().clone();
+        let offline = client.online_manager().clone();
+        let online_handle = window_event_listener(leptos::ev::online, move |_| online.set_status(NetworkStatus::Online));
+        let offline_handle = window_event_listener(leptos::ev::offline, move |_| offline.set_status(NetworkStatus::Offline));
+        on_cleanup(move || {
+            online_handle.remove();
+            offline_handle.remove();
+        });
+    }
+    
+    on_cleanup(move || client.online_manager().unsubscribe(listener_id));
     
     children()
 }
\ No newline at end of file
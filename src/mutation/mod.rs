@@ -4,38 +4,190 @@
 
 use leptos::prelude::*;
 use leptos::task::spawn_local;
+use std::any::Any;
 use std::future::Future;
-use serde::{Serialize, de::DeserializeOwned};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use futures::future::{join_all, LocalBoxFuture};
+use parking_lot::RwLock;
+use futures::FutureExt;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::client::QueryClient;
-use crate::retry::RetryConfig;
-use crate::types::QueryKeyPattern;
+use crate::optimistic::IdReconciler;
+use crate::retry::{QueryError, RetryConfig, execute_with_retry_if, is_retryable};
+use crate::types::{QueryKey, QueryKeyPattern};
+use crate::scheduler::FetchPriority;
+
+/// Generator for idempotency keys
+pub type IdempotencyKeyFn = Arc<dyn Fn() -> String + Send + Sync>;
+
+// Lifecycle callbacks are typed by the mutation's data, error, variables and context,
+// so a callback that doesn't fit the mutation fails to compile.
+type OnMutateFn<TVariables, TContext> = Arc<dyn Fn(&QueryClient, &TVariables) -> TContext + Send + Sync>;
+type OnSuccessFn<TData, TVariables, TContext> = Arc<dyn Fn(&QueryClient, &TData, &TVariables, Option<&TContext>) + Send + Sync>;
+type OnErrorFn<TError, TVariables, TContext> = Arc<dyn Fn(&QueryClient, &TError, &TVariables, Option<&TContext>) + Send + Sync>;
+type OnSettledFn<TData, TError, TVariables, TContext> =
+    Arc<dyn Fn(&QueryClient, Option<&TData>, Option<&TError>, &TVariables, Option<&TContext>) + Send + Sync>;
+type QueryUpdateFn<TData> = Arc<dyn Fn(&QueryClient, &TData) + Send + Sync>;
+type ReconcileIdsFn<TData, TVariables> =
+    Arc<dyn Fn(&QueryClient, &TVariables, &TData) -> Option<LocalBoxFuture<'static, ()>> + Send + Sync>;
+type RetryIfFn<TError> = Arc<dyn Fn(&TError) -> bool + Send + Sync>;
+
+/// Retry configuration of mutations that don't set their own
+fn default_retry() -> RetryConfig {
+    // Mutations aren't generally safe to repeat, so they only retry when asked to
+    RetryConfig {
+        max_retries: 0,
+        ..RetryConfig::default()
+    }
+}
 
 /// Options for configuring a mutation
-#[derive(Clone)]
-pub struct MutationOptions {
+///
+/// The type parameters are the mutation's data, error and variables, and the context
+/// returned by `on_mutate`; they are usually inferred from the mutation function.
+/// Retry, timeout and invalidation waiting set through the builder methods are never
+/// replaced by client defaults.
+pub struct MutationOptions<TData = (), TError = QueryError, TVariables = (), TContext = ()> {
     /// Whether the mutation should run
     pub enabled: bool,
     /// Retry configuration
     pub retry: RetryConfig,
+    pub(crate) retry_if: Option<RetryIfFn<TError>>,
+    /// Maximum duration of a single mutation attempt
+    pub timeout: Option<Duration>,
     /// Whether to invalidate queries on success
     pub invalidate_queries: Option<Vec<QueryKeyPattern>>,
+    /// Whether the mutation stays loading until invalidated active queries have refetched
+    pub await_invalidation: bool,
+    pub(crate) update_queries: Vec<QueryUpdateFn<TData>>,
+    pub(crate) reconcile_ids: Option<ReconcileIdsFn<TData, TVariables>>,
+    /// Generates the idempotency key shared by all attempts of one mutation
+    ///
+    /// Only [`use_idempotent_mutation`] passes the key to the mutation function.
+    pub idempotency_key: Option<IdempotencyKeyFn>,
+    /// Mutations sharing a scope run one at a time, in the order they were started
+    pub scope: Option<String>,
+    /// Key used to find this mutation in the [`MutationCache`]
+    pub mutation_key: Option<QueryKey>,
+    pub(crate) on_mutate: Option<OnMutateFn<TVariables, TContext>>,
+    pub(crate) on_success: Option<OnSuccessFn<TData, TVariables, TContext>>,
+    pub(crate) on_error: Option<OnErrorFn<TError, TVariables, TContext>>,
+    pub(crate) on_settled: Option<OnSettledFn<TData, TError, TVariables, TContext>>,
+    explicit: ExplicitOptions,
 }
 
-impl Default for MutationOptions {
+/// Options a mutation set through its builder methods
+#[derive(Clone, Copy, Debug, Default)]
+struct ExplicitOptions {
+    retry: bool,
+    timeout: bool,
+    await_invalidation: bool,
+}
+
+impl<TData, TError, TVariables> Default for MutationOptions<TData, TError, TVariables> {
     fn default() -> Self {
         Self {
             enabled: true,
-            retry: RetryConfig::default(),
+            retry: default_retry(),
+            retry_if: None,
+            timeout: None,
             invalidate_queries: None,
+            await_invalidation: false,
+            update_queries: Vec::new(),
+            reconcile_ids: None,
+            idempotency_key: None,
+            scope: None,
+            mutation_key: None,
+            on_mutate: None,
+            on_success: None,
+            on_error: None,
+            on_settled: None,
+            explicit: ExplicitOptions::default(),
+        }
+    }
+}
+
+impl<TData, TError, TVariables, TContext> Clone for MutationOptions<TData, TError, TVariables, TContext> {
+    fn clone(&self) -> Self {
+        Self {
+            enabled: self.enabled,
+            retry: self.retry.clone(),
+            retry_if: self.retry_if.clone(),
+            timeout: self.timeout,
+            invalidate_queries: self.invalidate_queries.clone(),
+            await_invalidation: self.await_invalidation,
+            update_queries: self.update_queries.clone(),
+            reconcile_ids: self.reconcile_ids.clone(),
+            idempotency_key: self.idempotency_key.clone(),
+            scope: self.scope.clone(),
+            mutation_key: self.mutation_key.clone(),
+            on_mutate: self.on_mutate.clone(),
+            on_success: self.on_success.clone(),
+            on_error: self.on_error.clone(),
+            on_settled: self.on_settled.clone(),
+            explicit: self.explicit,
         }
     }
 }
 
-impl MutationOptions {
+impl<TData, TError, TVariables> MutationOptions<TData, TError, TVariables> {
+    /// Run before the mutation starts; the returned context is passed to the other callbacks
+    ///
+    /// Callbacks registered before this one were typed without a context and see none.
+    pub fn on_mutate<TContext>(
+        self,
+        callback: impl Fn(&QueryClient, &TVariables) -> TContext + Send + Sync + 'static,
+    ) -> MutationOptions<TData, TError, TVariables, TContext>
+    where
+        TData: 'static,
+        TError: 'static,
+        TVariables: 'static,
+        TContext: 'static,
+    {
+        let on_success = self.on_success.clone().map(|on_success| -> OnSuccessFn<TData, TVariables, TContext> {
+            Arc::new(move |client, data, vars, _| on_success(client, data, vars, None))
+        });
+        let on_error = self.on_error.clone().map(|on_error| -> OnErrorFn<TError, TVariables, TContext> {
+            Arc::new(move |client, error, vars, _| on_error(client, error, vars, None))
+        });
+        let on_settled = self.on_settled.clone().map(|on_settled| -> OnSettledFn<TData, TError, TVariables, TContext> {
+            Arc::new(move |client, data, error, vars, _| on_settled(client, data, error, vars, None))
+        });
+        self.with_lifecycle(Some(Arc::new(callback)), on_success, on_error, on_settled)
+    }
+}
+
+impl<TData, TError, TVariables, TContext> MutationOptions<TData, TError, TVariables, TContext> {
     /// Create options with custom retry configuration
     pub fn with_retry(mut self, retry: RetryConfig) -> Self {
         self.retry = retry;
+        self.explicit.retry = true;
+        self
+    }
+    
+    /// Only retry errors the predicate accepts
+    ///
+    /// Without a predicate, `QueryError`s are retried by kind and other error types always are.
+    pub fn retry_if(mut self, predicate: impl Fn(&TError) -> bool + Send + Sync + 'static) -> Self {
+        self.retry_if = Some(Arc::new(predicate));
+        self
+    }
+    
+    /// Create options with a per-attempt timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.explicit.timeout = true;
+        self
+    }
+    
+    /// Let attempts run as long as they take, even if the client sets a default timeout
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = None;
+        self.explicit.timeout = true;
         self
     }
     
@@ -45,16 +197,438 @@ impl MutationOptions {
         self
     }
     
+    /// Keep the mutation loading until invalidated active queries have refetched
+    pub fn await_invalidation(mut self) -> Self {
+        self.await_invalidation = true;
+        self.explicit.await_invalidation = true;
+        self
+    }
+    
+    /// Write the mutation response into cached queries matching a pattern on success
+    ///
+    /// Returning `None` from the updater leaves a query unchanged.
+    /// See [`replace_by_id`], [`append_item`] and [`remove_by_id`] for common list updates.
+    pub fn update_queries<T>(
+        mut self,
+        pattern: QueryKeyPattern,
+        updater: impl Fn(T, &TData) -> Option<T> + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        self.update_queries.push(Arc::new(move |client, data| {
+            client.set_queries_data::<T>(&pattern, |old| updater(old, data));
+        }));
+        self
+    }
+    
+    /// Map the temporary ID of an optimistic create to the ID the server assigned on success
+    ///
+    /// The mapping is recorded before `update_queries` rules run, so they see the real ID.
+    pub fn reconcile_ids(
+        mut self,
+        reconciler: IdReconciler,
+        temp_id: impl Fn(&TVariables) -> Option<String> + Send + Sync + 'static,
+        real_id: impl Fn(&TData) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.reconcile_ids = Some(Arc::new(move |client, vars, data| {
+            let temp_id = temp_id(vars)?;
+            let real_id = real_id(data);
+            let reconciler = reconciler.clone();
+            let client = client.clone();
+            Some(async move {
+                reconciler.reconcile(&client, &temp_id, &real_id).await;
+            }.boxed_local())
+        }));
+        self
+    }
+    
+    /// Generate an idempotency key for each mutation, reused across its retries
+    pub fn with_idempotency_key(mut self, generate: impl Fn() -> String + Send + Sync + 'static) -> Self {
+        self.idempotency_key = Some(Arc::new(generate));
+        self
+    }
+    
+    /// Run this mutation after earlier mutations in the same scope have settled
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+    
+    /// Set the key this mutation is tracked under in the [`MutationCache`]
+    pub fn with_mutation_key(mut self, key: QueryKey) -> Self {
+        self.mutation_key = Some(key);
+        self
+    }
+    
+    /// Run when the mutation succeeds
+    pub fn on_success(
+        mut self,
+        callback: impl Fn(&QueryClient, &TData, &TVariables, Option<&TContext>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_success = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Run when the mutation fails, after all retries
+    pub fn on_error(
+        mut self,
+        callback: impl Fn(&QueryClient, &TError, &TVariables, Option<&TContext>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_error = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Run after the mutation either succeeded or failed
+    pub fn on_settled(
+        mut self,
+        callback: impl Fn(&QueryClient, Option<&TData>, Option<&TError>, &TVariables, Option<&TContext>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_settled = Some(Arc::new(callback));
+        self
+    }
+    
     /// Disable the mutation by default
     pub fn disabled(mut self) -> Self {
         self.enabled = false;
         self
     }
+    
+    /// Swap the lifecycle callbacks for ones with another context type, keeping every other option
+    fn with_lifecycle<TNewContext>(
+        self,
+        on_mutate: Option<OnMutateFn<TVariables, TNewContext>>,
+        on_success: Option<OnSuccessFn<TData, TVariables, TNewContext>>,
+        on_error: Option<OnErrorFn<TError, TVariables, TNewContext>>,
+        on_settled: Option<OnSettledFn<TData, TError, TVariables, TNewContext>>,
+    ) -> MutationOptions<TData, TError, TVariables, TNewContext> {
+        MutationOptions {
+            enabled: self.enabled,
+            retry: self.retry,
+            retry_if: self.retry_if,
+            timeout: self.timeout,
+            invalidate_queries: self.invalidate_queries,
+            await_invalidation: self.await_invalidation,
+            update_queries: self.update_queries,
+            reconcile_ids: self.reconcile_ids,
+            idempotency_key: self.idempotency_key,
+            scope: self.scope,
+            mutation_key: self.mutation_key,
+            on_mutate,
+            on_success,
+            on_error,
+            on_settled,
+            explicit: self.explicit,
+        }
+    }
+}
+
+/// Partial mutation options, filled in for the options a mutation doesn't set itself
+///
+/// Set on a [`QueryClientConfig`](crate::client::QueryClientConfig) for every mutation.
+#[derive(Clone, Debug, Default)]
+pub struct MutationDefaults {
+    /// Retry configuration
+    pub retry: Option<RetryConfig>,
+    /// Maximum duration of a single mutation attempt
+    pub timeout: Option<Duration>,
+    /// Whether mutations stay loading until invalidated active queries have refetched
+    pub await_invalidation: Option<bool>,
+}
+
+impl MutationDefaults {
+    /// Create defaults that leave every option alone
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Default retry configuration
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+    
+    /// Default per-attempt timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    
+    /// Keep mutations loading until invalidated active queries have refetched by default
+    pub fn await_invalidation(mut self) -> Self {
+        self.await_invalidation = Some(true);
+        self
+    }
+    
+    /// Fill in the options a mutation doesn't set itself
+    ///
+    /// An option counts as set if a builder method set it or it differs from [`MutationOptions::default`].
+    pub fn apply_to<TData, TError, TVariables, TContext>(
+        &self,
+        mut options: MutationOptions<TData, TError, TVariables, TContext>,
+    ) -> MutationOptions<TData, TError, TVariables, TContext> {
+        let explicit = options.explicit;
+        
+        if let Some(retry) = self.retry.clone().filter(|_| !explicit.retry && options.retry == default_retry()) {
+            options.retry = retry;
+        }
+        if !explicit.timeout && options.timeout.is_none() {
+            options.timeout = self.timeout;
+        }
+        if let Some(await_invalidation) = self.await_invalidation.filter(|_| !explicit.await_invalidation && !options.await_invalidation) {
+            options.await_invalidation = await_invalidation;
+        }
+        options
+    }
+}
+
+/// Status of a mutation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MutationStatus {
+    /// Mutation has not run yet or was reset
+    #[default]
+    Idle,
+    /// Mutation is waiting for earlier mutations in its scope
+    Queued,
+    /// Mutation is running
+    Pending,
+    /// Mutation is waiting for the network to come back
+    Paused,
+    /// Mutation completed successfully
+    Success,
+    /// Mutation failed with an error
+    Error,
 }
 
+/// Query updater that replaces the list item with the same id as the mutation response
+pub fn replace_by_id<T, TId>(
+    id: impl Fn(&T) -> TId + Send + Sync + 'static,
+) -> impl Fn(Vec<T>, &T) -> Option<Vec<T>> + Send + Sync + 'static
+where
+    T: Clone,
+    TId: PartialEq,
+{
+    move |items, updated| {
+        let updated_id = id(updated);
+        Some(
+            items
+                .into_iter()
+                .map(|item| if id(&item) == updated_id { updated.clone() } else { item })
+                .collect(),
+        )
+    }
+}
+
+/// Query updater that appends the mutation response to the list
+pub fn append_item<T: Clone>() -> impl Fn(Vec<T>, &T) -> Option<Vec<T>> + Send + Sync + 'static {
+    |mut items, created| {
+        items.push(created.clone());
+        Some(items)
+    }
+}
+
+/// Query updater that removes the list item whose id matches the id of the mutation response
+pub fn remove_by_id<T, TData, TId>(
+    item_id: impl Fn(&T) -> TId + Send + Sync + 'static,
+    removed_id: impl Fn(&TData) -> TId + Send + Sync + 'static,
+) -> impl Fn(Vec<T>, &TData) -> Option<Vec<T>> + Send + Sync + 'static
+where
+    TId: PartialEq,
+{
+    move |items, removed| {
+        let removed_id = removed_id(removed);
+        Some(items.into_iter().filter(|item| item_id(item) != removed_id).collect())
+    }
+}
+
+/// Identifier of a mutation in the [`MutationCache`]
+pub type MutationId = u64;
+
+/// State of a mutation tracked by the [`MutationCache`]
+#[derive(Clone)]
+pub struct MutationState {
+    /// Mutation ID
+    pub id: MutationId,
+    /// Key given through [`MutationOptions::with_mutation_key`]
+    pub mutation_key: Option<QueryKey>,
+    /// Current status
+    pub status: MutationStatus,
+    /// When the mutation was submitted
+    pub submitted_at: Instant,
+    /// When the status last changed
+    pub updated_at: Instant,
+    variables: Arc<dyn Any + Send + Sync>,
+    data: Option<Arc<dyn Any + Send + Sync>>,
+    error: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl MutationState {
+    /// Get the variables, if they are of type `T`
+    pub fn variables<T: 'static>(&self) -> Option<&T> {
+        self.variables.downcast_ref()
+    }
+    
+    /// Get the result data, if the mutation succeeded with type `T`
+    pub fn data<T: 'static>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(|data| data.downcast_ref())
+    }
+    
+    /// Get the error, if the mutation failed with type `T`
+    pub fn error<T: 'static>(&self) -> Option<&T> {
+        self.error.as_ref().and_then(|error| error.downcast_ref())
+    }
+    
+    /// Check if the mutation is queued, paused or running
+    pub fn is_mutating(&self) -> bool {
+        matches!(self.status, MutationStatus::Queued | MutationStatus::Pending | MutationStatus::Paused)
+    }
+}
+
+/// Filter for mutations in the [`MutationCache`]
+#[derive(Debug, Clone, Default)]
+pub struct MutationFilter {
+    /// Only match mutations whose key matches this pattern
+    pub mutation_key: Option<QueryKeyPattern>,
+    /// Only match mutations with this status
+    pub status: Option<MutationStatus>,
+}
+
+impl MutationFilter {
+    /// Create a filter matching all mutations
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Only match mutations whose key matches a pattern
+    pub fn with_key(mut self, pattern: QueryKeyPattern) -> Self {
+        self.mutation_key = Some(pattern);
+        self
+    }
+    
+    /// Only match mutations with a status
+    pub fn with_status(mut self, status: MutationStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+    
+    /// Check if a mutation matches the filter
+    pub fn matches(&self, mutation: &MutationState) -> bool {
+        let key_matches = match (&self.mutation_key, &mutation.mutation_key) {
+            (Some(pattern), Some(key)) => key.matches_pattern(pattern),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        key_matches && self.status.map_or(true, |status| mutation.status == status)
+    }
+}
+
+/// Listener notified whenever a mutation in the cache changes
+type MutationListener = Arc<dyn Fn() + Send + Sync>;
+
+/// Cache of all mutations started through a [`QueryClient`]
+#[derive(Clone)]
+pub struct MutationCache {
+    mutations: Arc<RwLock<Vec<MutationState>>>,
+    listeners: Arc<RwLock<HashMap<u64, MutationListener>>>,
+    next_id: Arc<AtomicU64>,
+    cache_time: Duration,
+}
+
+impl MutationCache {
+    /// Create a new mutation cache; settled mutations are dropped after `cache_time`
+    pub fn new(cache_time: Duration) -> Self {
+        Self {
+            mutations: Arc::new(RwLock::new(Vec::new())),
+            listeners: Arc::new(RwLock::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+            cache_time,
+        }
+    }
+    
+    /// Start tracking a mutation
+    pub(crate) fn add(&self, mutation_key: Option<QueryKey>, variables: Arc<dyn Any + Send + Sync>) -> MutationId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        {
+            let mut mutations = self.mutations.write();
+            mutations.retain(|mutation| mutation.is_mutating() || mutation.updated_at.elapsed() < self.cache_time);
+            mutations.push(MutationState {
+                id,
+                mutation_key,
+                status: MutationStatus::Pending,
+                submitted_at: now,
+                updated_at: now,
+                variables,
+                data: None,
+                error: None,
+            });
+        }
+        self.notify();
+        id
+    }
+    
+    /// Update a tracked mutation
+    pub(crate) fn update(&self, id: MutationId, update: impl FnOnce(&mut MutationState)) {
+        {
+            let mut mutations = self.mutations.write();
+            let Some(mutation) = mutations.iter_mut().find(|mutation| mutation.id == id) else { return };
+            update(mutation);
+            mutation.updated_at = Instant::now();
+        }
+        self.notify();
+    }
+    
+    /// Get the mutations matching a filter, oldest first
+    pub fn find_all(&self, filter: &MutationFilter) -> Vec<MutationState> {
+        let mutations = self.mutations.read();
+        mutations.iter().filter(|mutation| filter.matches(mutation)).cloned().collect()
+    }
+    
+    /// Count the unsettled mutations matching a filter
+    pub fn is_mutating(&self, filter: &MutationFilter) -> usize {
+        let mutations = self.mutations.read();
+        mutations.iter().filter(|mutation| mutation.is_mutating() && filter.matches(mutation)).count()
+    }
+    
+    /// Stop tracking a mutation
+    pub fn remove(&self, id: MutationId) {
+        self.mutations.write().retain(|mutation| mutation.id != id);
+        self.notify();
+    }
+    
+    /// Stop tracking all mutations
+    pub fn clear(&self) {
+        self.mutations.write().clear();
+        self.notify();
+    }
+    
+    /// Call a listener whenever a mutation changes
+    pub fn subscribe(&self, listener: impl Fn() + Send + Sync + 'static) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.listeners.write().insert(id, Arc::new(listener));
+        id
+    }
+    
+    /// Remove a listener
+    pub fn unsubscribe(&self, id: u64) {
+        self.listeners.write().remove(&id);
+    }
+    
+    fn notify(&self) {
+        let listeners: Vec<MutationListener> = self.listeners.read().values().cloned().collect();
+        for listener in listeners {
+            listener();
+        }
+    }
+}
+
+/// Runs a mutation and resolves with its result
+type MutateAsyncFn<TData, TError, TVariables> =
+    Arc<dyn Fn(TVariables) -> LocalBoxFuture<'static, Result<TData, TError>> + Send + Sync>;
+
 /// Result of a mutation hook
 #[derive(Clone)]
-pub struct MutationResult<TData: 'static + Send + Sync, TError: 'static + Send + Sync, TVariables: 'static> {
+pub struct MutationResult<TData: 'static + Send + Sync, TError: 'static + Send + Sync, TVariables: 'static + Send + Sync> {
     /// The mutation data
     pub data: Signal<Option<TData>>,
     /// Error if any
@@ -65,75 +639,295 @@ pub struct MutationResult<TData: 'static + Send + Sync, TError: 'static + Send +
     pub is_success: Signal<bool>,
     /// Whether the mutation failed
     pub is_error: Signal<bool>,
+    /// Current mutation status
+    pub status: Signal<MutationStatus>,
+    /// Variables of the latest mutation
+    pub variables: Signal<Option<TVariables>>,
+    /// When the latest mutation was submitted
+    pub submitted_at: Signal<Option<Instant>>,
     
     // Actions
     /// Execute the mutation
     pub mutate: Callback<TVariables>,
+    /// Clear the data, error and variables of the latest mutation
+    pub reset: Callback<()>,
+    mutate_async: MutateAsyncFn<TData, TError, TVariables>,
+}
+
+impl<TData, TError, TVariables> MutationResult<TData, TError, TVariables>
+where
+    TData: 'static + Send + Sync,
+    TError: 'static + Send + Sync,
+    TVariables: 'static + Send + Sync,
+{
+    /// Execute the mutation and wait for its result
+    pub fn mutate_async(&self, vars: TVariables) -> impl Future<Output = Result<TData, TError>> {
+        (self.mutate_async)(vars)
+    }
 }
 
 /// Main mutation hook
+///
+/// The mutation function takes no idempotency key, so there is nowhere to send one: a
+/// [`MutationOptions::idempotency_key`] is not generated and a warning is logged. Use
+/// [`use_idempotent_mutation`] to receive the key.
 pub fn use_mutation<TData, TError, TVariables, F, Fut>(
     mutation_fn: F,
-    options: MutationOptions,
+    options: MutationOptions<TData, TError, TVariables, impl Send + Sync + 'static>,
 ) -> MutationResult<TData, TError, TVariables>
 where
     TData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
-    TError: Clone + Send + Sync + 'static,
+    TError: Clone + Send + Sync + From<QueryError> + 'static,
     TVariables: Clone + Send + Sync + 'static,
     F: Fn(TVariables) -> Fut + Send + Sync + 'static + Clone,
     Fut: Future<Output = Result<TData, TError>> + 'static,
+{
+    let mut options = options;
+    if options.idempotency_key.take().is_some() {
+        leptos::logging::warn!(
+            "use_mutation can't pass an idempotency key to its mutation function; use use_idempotent_mutation instead"
+        );
+    }
+    mutation_hook(move |vars, _| mutation_fn(vars), options)
+}
+
+/// Mutation hook that passes an idempotency key to the mutation function
+///
+/// The key is generated once per `mutate` call and reused for every retry, so the
+/// server can recognise repeated requests. Keys come from
+/// [`MutationOptions::idempotency_key`], or are random UUIDs if it isn't set.
+pub fn use_idempotent_mutation<TData, TError, TVariables, F, Fut>(
+    mutation_fn: F,
+    options: MutationOptions<TData, TError, TVariables, impl Send + Sync + 'static>,
+) -> MutationResult<TData, TError, TVariables>
+where
+    TData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    TError: Clone + Send + Sync + From<QueryError> + 'static,
+    TVariables: Clone + Send + Sync + 'static,
+    F: Fn(TVariables, String) -> Fut + Send + Sync + 'static + Clone,
+    Fut: Future<Output = Result<TData, TError>> + 'static,
+{
+    let mut options = options;
+    if options.idempotency_key.is_none() {
+        options.idempotency_key = Some(Arc::new(|| uuid::Uuid::new_v4().to_string()));
+    }
+    
+    mutation_hook(
+        move |vars, key: Option<String>| mutation_fn(vars, key.unwrap_or_default()),
+        options,
+    )
+}
+
+/// Run one mutation through its whole lifecycle
+///
+/// `on_status` sees every status change and `on_result` sees the result before the callbacks run.
+async fn execute_mutation<TData, TError, TVariables, TContext, F, Fut>(
+    client: QueryClient,
+    options: MutationOptions<TData, TError, TVariables, TContext>,
+    mutation_fn: F,
+    mut vars: TVariables,
+    on_status: impl Fn(MutationStatus),
+    on_result: impl Fn(&Result<TData, TError>),
+) -> Result<TData, TError>
+where
+    TData: Clone + Send + Sync + 'static,
+    TError: Clone + Send + Sync + From<QueryError> + 'static,
+    TVariables: Clone + Send + Sync + 'static,
+    TContext: 'static,
+    F: Fn(TVariables, Option<String>) -> Fut,
+    Fut: Future<Output = Result<TData, TError>> + 'static,
+{
+    let mutation_cache = client.mutation_cache().clone();
+    let mutation_id = mutation_cache.add(options.mutation_key.clone(), Arc::new(vars.clone()));
+    let set_status = |status: MutationStatus| {
+        mutation_cache.update(mutation_id, |mutation| mutation.status = status);
+        on_status(status);
+    };
+    
+    // Wait for earlier mutations in the same scope to settle
+    let _permit = match &options.scope {
+        Some(scope) => {
+            let scheduler = client.mutation_scope(scope);
+            let ticket = scheduler.enqueue(&QueryKey::from(scope.as_str()), FetchPriority::Normal);
+            if scheduler.queued().iter().any(|queued| queued.id == ticket.id()) {
+                set_status(MutationStatus::Queued);
+            }
+            ticket.wait().await.ok()
+        }
+        None => None,
+    };
+    set_status(MutationStatus::Pending);
+    
+    let context = options.on_mutate.as_ref().map(|on_mutate| on_mutate(&client, &vars));
+    
+    // Mutations made while offline wait for the network, in the order they were made
+    let (_offline_permit, persisted) = if client.online_manager().is_online() {
+        (None, None)
+    } else {
+        set_status(MutationStatus::Paused);
+        let persisted = match &options.mutation_key {
+            Some(mutation_key) => client.persist_paused_mutation(mutation_key, &vars).await,
+            None => None,
+        };
+        
+        let permit = client.paused_mutations().acquire(&QueryKey::from("paused"), FetchPriority::Normal).await.ok();
+        client.online_manager().wait_online().await;
+        if let Some(id) = &persisted {
+            // Run with the persisted variables, which ID reconciliation may have rewritten
+            if let Some(stored) = client.paused_mutation_variables(id).await.and_then(|stored| stored.downcast::<TVariables>().ok()) {
+                vars = *stored;
+            }
+        }
+        
+        set_status(MutationStatus::Pending);
+        (permit, persisted)
+    };
+    
+    // Every attempt of this mutation shares one idempotency key
+    let idempotency_key = options.idempotency_key.as_ref().map(|generate| generate());
+    
+    let result = execute_with_retry_if(
+        || mutation_fn(vars.clone(), idempotency_key.clone()),
+        &options.retry,
+        options.timeout,
+        |error: &TError| match &options.retry_if {
+            Some(retry_if) => retry_if(error),
+            None => is_retryable(error, &options.retry),
+        },
+    ).await;
+    on_result(&result);
+    
+    // A persisted mutation that lost the connection again stays queued for the next resume
+    if let Some(id) = &persisted {
+        client.settle_paused_mutation(id, !is_connection_failure(&result)).await;
+    }
+    
+    match &result {
+        Ok(result_data) => {
+            if let Some(reconcile) = options.reconcile_ids.as_ref().and_then(|reconcile| reconcile(&client, &vars, result_data)) {
+                reconcile.await;
+            }
+            
+            for update in &options.update_queries {
+                update(&client, result_data);
+            }
+            
+            if let Some(on_success) = &options.on_success {
+                on_success(&client, result_data, &vars, context.as_ref());
+            }
+            
+            // Invalidate queries if specified
+            if let Some(patterns) = &options.invalidate_queries {
+                if options.await_invalidation {
+                    join_all(patterns.iter().map(|pattern| client.invalidate_and_refetch_queries(pattern))).await;
+                } else {
+                    for refetch in patterns.iter().flat_map(|pattern| client.invalidate_queries(pattern)) {
+                        spawn_local(refetch);
+                    }
+                }
+            }
+        }
+        Err(err) => {
+            if let Some(on_error) = &options.on_error {
+                on_error(&client, err, &vars, context.as_ref());
+            }
+        }
+    }
+    
+    if let Some(on_settled) = &options.on_settled {
+        on_settled(&client, result.as_ref().ok(), result.as_ref().err(), &vars, context.as_ref());
+    }
+    
+    mutation_cache.update(mutation_id, |mutation| match &result {
+        Ok(data) => mutation.data = Some(Arc::new(data.clone())),
+        Err(err) => mutation.error = Some(Arc::new(err.clone())),
+    });
+    set_status(if result.is_ok() { MutationStatus::Success } else { MutationStatus::Error });
+    
+    result
+}
+
+/// Shared implementation of the mutation hooks
+fn mutation_hook<TData, TError, TVariables, TContext, F, Fut>(
+    mutation_fn: F,
+    options: MutationOptions<TData, TError, TVariables, TContext>,
+) -> MutationResult<TData, TError, TVariables>
+where
+    TData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    TError: Clone + Send + Sync + From<QueryError> + 'static,
+    TVariables: Clone + Send + Sync + 'static,
+    TContext: 'static,
+    F: Fn(TVariables, Option<String>) -> Fut + Send + Sync + 'static + Clone,
+    Fut: Future<Output = Result<TData, TError>> + 'static,
 {
     // Create reactive state
     let (data, set_data) = signal(None::<TData>);
     let (error, set_error) = signal(None::<TError>);
-    let (is_loading, set_loading) = signal(false);
+    let (status, set_status) = signal(MutationStatus::Idle);
+    let (variables, set_variables) = signal(None::<TVariables>);
+    let (submitted_at, set_submitted_at) = signal(None::<Instant>);
+    
+    // Only the latest mutation since the last reset updates the state
+    let generation = StoredValue::new(0_u64);
 
     // Get query client from context
     let client = use_context::<QueryClient>().expect("QueryClient not found in context");
+    let options = client.resolve_mutation_options(options);
     
     // Create mutation function
-    let execute_mutation = {
-        let client = client.clone();
-        let mutation_fn = mutation_fn.clone();
-        let options = options.clone();
+    let run_mutation = move |vars: TVariables| {
+        generation.update_value(|generation| *generation += 1);
+        let current = generation.try_get_value();
+        let is_current = move || generation.try_get_value() == current;
         
-        move |vars: TVariables| {
-            let _client = client.clone();
-            let mutation_fn = mutation_fn.clone();
-            let options = options.clone();
-            
-            spawn_local(async move {
-                set_loading.set(true);
-                set_error.set(None);
-                
-                // Execute the mutation directly without retry for now
-                let result = mutation_fn(vars.clone()).await;
-                
-                match result {
-                    Ok(result_data) => {
-                        set_data.set(Some(result_data));
-                        
-                        // Invalidate queries if specified
-                        if let Some(patterns) = &options.invalidate_queries {
-                            for _pattern in patterns {
-                                // TODO: Implement invalidate_queries method
-                                // client.invalidate_queries(pattern);
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        set_error.set(Some(err));
+        set_variables.set(Some(vars.clone()));
+        set_submitted_at.set(Some(Instant::now()));
+        set_error.set(None);
+        
+        execute_mutation(
+            client.clone(),
+            options.clone(),
+            mutation_fn.clone(),
+            vars,
+            move |status| {
+                if is_current() {
+                    set_status.set(status);
+                }
+            },
+            move |result| {
+                if is_current() {
+                    match result {
+                        Ok(result_data) => set_data.set(Some(result_data.clone())),
+                        Err(err) => set_error.set(Some(err.clone())),
                     }
                 }
-                
-                set_loading.set(false);
+            },
+        )
+    };
+    
+    let execute_mutation = {
+        let run_mutation = run_mutation.clone();
+        move |vars: TVariables| {
+            let mutation = run_mutation(vars);
+            spawn_local(async move {
+                let _ = mutation.await;
             });
         }
     };
     
+    let reset = move |_: ()| {
+        generation.update_value(|generation| *generation += 1);
+        set_data.set(None);
+        set_error.set(None);
+        set_variables.set(None);
+        set_submitted_at.set(None);
+        set_status.set(MutationStatus::Idle);
+    };
+    
     // Create computed signals
-    let is_success = Memo::new(move |_| data.get().is_some() && error.get().is_none());
-    let is_error = Memo::new(move |_| error.get().is_some());
+    let is_loading = Memo::new(move |_| status.get() == MutationStatus::Pending);
+    let is_success = Memo::new(move |_| status.get() == MutationStatus::Success);
+    let is_error = Memo::new(move |_| status.get() == MutationStatus::Error);
     
     // Create result
     MutationResult {
@@ -142,42 +936,644 @@ where
         is_loading: is_loading.into(),
         is_success: is_success.into(),
         is_error: is_error.into(),
+        status: status.into(),
+        variables: variables.into(),
+        submitted_at: submitted_at.into(),
         mutate: Callback::new(execute_mutation),
+        reset: Callback::new(reset),
+        mutate_async: Arc::new(move |vars| run_mutation(vars).boxed_local()),
+    }
+}
+
+/// A mutation persisted while offline
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PausedMutation {
+    pub(crate) mutation_key: QueryKey,
+    pub(crate) variables: Vec<u8>,
+}
+
+/// Serializes the variables of a mutation, if they are of the registered type
+type SerializeVariablesFn = Arc<dyn Fn(&dyn Any) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Reads back the variables of a persisted mutation
+type DeserializeVariablesFn = Arc<dyn Fn(&[u8]) -> Option<Box<dyn Any>> + Send + Sync>;
+
+/// Runs a persisted mutation from its serialized variables, resolving to whether it settled
+type ResumeFn = Arc<dyn Fn(QueryClient, &[u8]) -> Option<LocalBoxFuture<'static, bool>> + Send + Sync>;
+
+/// Check if a mutation failed because the network or server couldn't be reached
+fn is_connection_failure<TData, TError: 'static>(result: &Result<TData, TError>) -> bool {
+    let Err(error) = result else { return false };
+    matches!(
+        (error as &dyn Any).downcast_ref::<QueryError>(),
+        Some(QueryError::NetworkError(_) | QueryError::TimeoutError(_))
+    )
+}
+
+/// Mutation function and options registered for a mutation key
+#[derive(Clone)]
+pub(crate) struct RegisteredMutation {
+    serialize_variables: SerializeVariablesFn,
+    deserialize_variables: DeserializeVariablesFn,
+    resume: ResumeFn,
+}
+
+impl RegisteredMutation {
+    pub(crate) fn new<TData, TError, TVariables, TContext, F, Fut>(
+        mutation_key: QueryKey,
+        mutation_fn: F,
+        options: MutationOptions<TData, TError, TVariables, TContext>,
+    ) -> Self
+    where
+        TData: Clone + Send + Sync + 'static,
+        TError: Clone + Send + Sync + From<QueryError> + 'static,
+        TVariables: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+        TContext: 'static,
+        F: Fn(TVariables) -> Fut + Send + Sync + 'static + Clone,
+        Fut: Future<Output = Result<TData, TError>> + 'static,
+    {
+        let options = options.with_mutation_key(mutation_key);
+        Self {
+            serialize_variables: Arc::new(|vars| {
+                vars.downcast_ref::<TVariables>().and_then(|vars| bincode::serialize(vars).ok())
+            }),
+            deserialize_variables: Arc::new(|variables| {
+                bincode::deserialize::<TVariables>(variables).ok().map(|vars| Box::new(vars) as Box<dyn Any>)
+            }),
+            resume: Arc::new(move |client, variables| {
+                let vars = bincode::deserialize::<TVariables>(variables).ok()?;
+                let mutation_fn = mutation_fn.clone();
+                let mutation = execute_mutation(client, options.clone(), move |vars, _| mutation_fn(vars), vars, |_| {}, |_| {});
+                Some(mutation.map(|result| !is_connection_failure(&result)).boxed_local())
+            }),
+        }
+    }
+    
+    /// Serialize the variables of a mutation for persistence
+    pub(crate) fn serialize_variables(&self, vars: &dyn Any) -> Option<Vec<u8>> {
+        (self.serialize_variables)(vars)
     }
+    
+    /// Read back the variables of a persisted mutation
+    pub(crate) fn deserialize_variables(&self, variables: &[u8]) -> Option<Box<dyn Any>> {
+        (self.deserialize_variables)(variables)
+    }
+    
+    /// Run a persisted mutation; `None` if its variables can't be read back
+    ///
+    /// Resolves to false if the mutation failed to reach the server and should be resumed again later.
+    pub(crate) fn resume(&self, client: QueryClient, variables: &[u8]) -> Option<LocalBoxFuture<'static, bool>> {
+        (self.resume)(client, variables)
+    }
+}
+
+/// Number of unsettled mutations matching a filter, across all components
+pub fn use_is_mutating(filter: MutationFilter) -> Signal<usize> {
+    let client = use_context::<QueryClient>().expect("QueryClient not found in context");
+    let cache = client.mutation_cache().clone();
+    
+    let (count, set_count) = signal(cache.is_mutating(&filter));
+    let listener_id = {
+        let cache = cache.clone();
+        cache.clone().subscribe(move || {
+            let current = cache.is_mutating(&filter);
+            if count.try_get_untracked() != Some(current) {
+                set_count.set(current);
+            }
+        })
+    };
+    on_cleanup(move || cache.unsubscribe(listener_id));
+    
+    count.into()
+}
+
+/// Selected state of the mutations matching a filter, across all components
+pub fn use_mutation_state<R>(
+    filter: MutationFilter,
+    select: impl Fn(&MutationState) -> R + Send + Sync + 'static,
+) -> Signal<Vec<R>>
+where
+    R: Clone + Send + Sync + 'static,
+{
+    let client = use_context::<QueryClient>().expect("QueryClient not found in context");
+    let cache = client.mutation_cache().clone();
+    let select_all = move |cache: &MutationCache| cache.find_all(&filter).iter().map(&select).collect::<Vec<R>>();
+    
+    let (state, set_state) = signal(select_all(&cache));
+    let listener_id = {
+        let cache = cache.clone();
+        cache.clone().subscribe(move || set_state.set(select_all(&cache)))
+    };
+    on_cleanup(move || cache.unsubscribe(listener_id));
+    
+    state.into()
+}
+
+/// Context of an optimistic mutation, wrapping the caller's own `on_mutate` context
+struct OptimisticContext<TContext> {
+    update_id: Option<String>,
+    context: Option<TContext>,
 }
 
 /// Hook for optimistic updates
-pub fn use_optimistic_mutation<TData, TError, TVariables, F, Fut>(
+///
+/// `optimistic_update` computes the data shown under `key` while the mutation runs.
+/// The previous cache entry is restored if the mutation fails.
+pub fn use_optimistic_mutation<TData, TError, TVariables, T, F, Fut>(
     mutation_fn: F,
-    options: MutationOptions,
-    _on_success: impl Fn(&TData) + Send + Sync + 'static,
-    _on_error: impl Fn(&TError) + Send + Sync + 'static,
+    key: QueryKey,
+    optimistic_update: impl Fn(&TVariables, Option<&T>) -> T + Send + Sync + 'static,
+    options: MutationOptions<TData, TError, TVariables, impl Send + Sync + 'static>,
 ) -> MutationResult<TData, TError, TVariables>
 where
     TData: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
-    TError: Clone + Send + Sync + 'static,
+    TError: Clone + Send + Sync + From<QueryError> + 'static,
     TVariables: Clone + Send + Sync + 'static,
+    T: Serialize + DeserializeOwned + 'static,
     F: Fn(TVariables) -> Fut + Send + Sync + 'static + Clone,
     Fut: Future<Output = Result<TData, TError>> + 'static,
 {
-    // For now, just return the regular mutation
-    // TODO: Implement optimistic updates
-    use_mutation(mutation_fn, options)
+    use_mutation(mutation_fn, optimistic_options(key, optimistic_update, options))
+}
+
+/// Add applying, confirming and rolling back the optimistic update to the lifecycle callbacks
+fn optimistic_options<TData, TError, TVariables, TContext, T>(
+    key: QueryKey,
+    optimistic_update: impl Fn(&TVariables, Option<&T>) -> T + Send + Sync + 'static,
+    options: MutationOptions<TData, TError, TVariables, TContext>,
+) -> MutationOptions<TData, TError, TVariables, OptimisticContext<TContext>>
+where
+    TData: 'static,
+    TError: 'static,
+    TVariables: 'static,
+    TContext: 'static,
+    T: Serialize + DeserializeOwned + 'static,
+{
+    // Wrap the caller's callbacks so they still see their own context
+    let user_on_mutate = options.on_mutate.clone();
+    let user_on_success = options.on_success.clone();
+    let user_on_error = options.on_error.clone();
+    let user_on_settled = options.on_settled.clone();
+    
+    // Updates live in the client so ID reconciliation can rewrite their keys and snapshots
+    let on_mutate: OnMutateFn<TVariables, OptimisticContext<TContext>> = Arc::new(move |client, vars| {
+        let snapshot = client.get_cache_entry(&key);
+        let current = snapshot.as_ref().and_then(|entry| entry.get_data::<T>().ok());
+        let update_id = client.set_query_data(&key, optimistic_update(vars, current.as_ref())).ok()
+            .and_then(|_| client.get_cache_entry(&key))
+            .map(|entry| {
+                let update_id = client.optimistic_updates().apply_update(&key, entry, snapshot);
+                if let Some(devtools) = client.devtools() {
+                    devtools.record_optimistic_update(&key, &update_id);
+                }
+                update_id
+            });
+        let context = user_on_mutate.as_ref().map(|on_mutate| on_mutate(client, vars));
+        
+        OptimisticContext { update_id, context }
+    });
+    
+    let on_success: OnSuccessFn<TData, TVariables, OptimisticContext<TContext>> = Arc::new(move |client, data, vars, context| {
+        if let Some(update_id) = context.and_then(|context| context.update_id.as_deref()) {
+            let updates = client.optimistic_updates();
+            if let Some(update) = updates.get_update(update_id).filter(|_| updates.confirm_update(update_id).is_ok()) {
+                if let Some(devtools) = client.devtools() {
+                    devtools.record_optimistic_confirm(&update.key, update_id);
+                }
+            }
+        }
+        if let Some(on_success) = &user_on_success {
+            on_success(client, data, vars, context.and_then(|context| context.context.as_ref()));
+        }
+    });
+    
+    let on_error: OnErrorFn<TError, TVariables, OptimisticContext<TContext>> = Arc::new(move |client, error, vars, context| {
+        if let Some(update_id) = context.and_then(|context| context.update_id.as_deref()) {
+            let updates = client.optimistic_updates();
+            // The key may have been moved to a real ID since the update was applied
+            if let Some(update) = updates.get_update(update_id) {
+                if let Ok(snapshot) = updates.rollback_update(update_id) {
+                    client.restore_cache_entry(&update.key, snapshot);
+                    if let Some(devtools) = client.devtools() {
+                        devtools.record_optimistic_rollback(&update.key, update_id);
+                    }
+                }
+            }
+        }
+        if let Some(on_error) = &user_on_error {
+            on_error(client, error, vars, context.and_then(|context| context.context.as_ref()));
+        }
+    });
+    
+    let on_settled = user_on_settled.map(|on_settled| -> OnSettledFn<TData, TError, TVariables, OptimisticContext<TContext>> {
+        Arc::new(move |client, data, error, vars, context| {
+            on_settled(client, data, error, vars, context.and_then(|context| context.context.as_ref()));
+        })
+    });
+    
+    options.with_lifecycle(Some(on_mutate), Some(on_success), Some(on_error), on_settled)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
     use crate::types::QueryKey;
     
     #[test]
     fn test_mutation_options_builder() {
-        let options = MutationOptions::default()
+        let options: MutationOptions = MutationOptions::default()
             .with_retry(RetryConfig::new(5, Duration::from_secs(2)))
-            .invalidate_queries(vec![QueryKeyPattern::Exact(QueryKey::from("users"))]);
+            .with_timeout(Duration::from_secs(10))
+            .invalidate_queries(vec![QueryKeyPattern::Exact(QueryKey::from("users"))])
+            .await_invalidation();
         
         assert_eq!(options.retry.max_retries, 5);
+        assert_eq!(options.timeout, Some(Duration::from_secs(10)));
         assert!(options.invalidate_queries.is_some());
+        assert!(options.await_invalidation);
+    }
+    
+    #[test]
+    fn test_lifecycle_callbacks_receive_context() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        let successes = Arc::new(AtomicUsize::new(0));
+        let seen = successes.clone();
+        let options = MutationOptions::<String, QueryError, u32>::default()
+            .on_mutate(|client: &QueryClient, vars: &u32| {
+                client.set_query_data(&QueryKey::from("count"), *vars).unwrap();
+                vars * 10
+            })
+            .on_success(move |_: &QueryClient, data: &String, vars: &u32, context: Option<&u32>| {
+                assert_eq!(data, "saved");
+                assert_eq!(context, Some(&(vars * 10)));
+                seen.fetch_add(1, Ordering::SeqCst);
+            });
+        
+        let client = QueryClient::new();
+        let vars = 4_u32;
+        let data = "saved".to_string();
+        let context = (options.on_mutate.unwrap())(&client, &vars);
+        (options.on_success.unwrap())(&client, &data, &vars, Some(&context));
+        
+        assert_eq!(successes.load(Ordering::SeqCst), 1);
+        assert_eq!(client.get_query_data::<u32>(&QueryKey::from("count")), Some(4));
+    }
+    
+    #[test]
+    fn test_optimistic_update_rolls_back_on_error() {
+        let devtools = Arc::new(crate::devtools::DevToolsManager::new(Default::default()));
+        let client = QueryClient::new().with_devtools(devtools.clone());
+        let key = QueryKey::from("todos");
+        client.set_query_data(&key, vec!["a".to_string()]).unwrap();
+        
+        let options = optimistic_options(key.clone(), |todo: &String, todos: Option<&Vec<String>>| {
+            let mut todos = todos.cloned().unwrap_or_default();
+            todos.push(todo.clone());
+            todos
+        }, MutationOptions::<(), QueryError, String>::default());
+        
+        let vars = "b".to_string();
+        let context = (options.on_mutate.unwrap())(&client, &vars);
+        assert_eq!(client.get_query_data::<Vec<String>>(&key), Some(vec!["a".to_string(), "b".to_string()]));
+        
+        let error = QueryError::NetworkError("offline".to_string());
+        (options.on_error.unwrap())(&client, &error, &vars, Some(&context));
+        assert_eq!(client.get_query_data::<Vec<String>>(&key), Some(vec!["a".to_string()]));
+        
+        let events = devtools.get_event_history();
+        assert!(matches!(events[0], crate::devtools::DevToolsEvent::OptimisticUpdate { .. }));
+        assert!(matches!(events[1], crate::devtools::DevToolsEvent::OptimisticRollback { .. }));
+    }
+    
+    #[test]
+    fn test_rollback_after_reconciliation_restores_real_id() {
+        use crate::optimistic::generate_temp_id;
+        use crate::types::QueryKeyPattern;
+        
+        #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+        struct Todo {
+            id: String,
+            title: String,
+        }
+        
+        let client = QueryClient::new();
+        let temp_id = generate_temp_id();
+        let temp_key = QueryKey::new(["todo", temp_id.as_str()]);
+        client.set_query_data(&temp_key, Todo { id: temp_id.clone(), title: "milk".to_string() }).unwrap();
+        
+        // An edit of the created todo that fails after the create confirmed
+        let options = optimistic_options(temp_key.clone(), |title: &String, todo: Option<&Todo>| {
+            Todo { title: title.clone(), ..todo.cloned().unwrap() }
+        }, MutationOptions::<(), QueryError, String>::default());
+        let vars = "bread".to_string();
+        let context = (options.on_mutate.unwrap())(&client, &vars);
+        
+        let reconciler = IdReconciler::new().with_query_rewriter(QueryKeyPattern::Prefix(QueryKey::from("todo")), |todo: &mut Todo, temp_id, real_id| {
+            let changed = todo.id == temp_id;
+            if changed {
+                todo.id = real_id.to_string();
+            }
+            changed
+        });
+        // The moved key, its rewritten data and the pending optimistic update
+        assert_eq!(futures::executor::block_on(reconciler.reconcile(&client, &temp_id, "42")), 3);
+        
+        let error = QueryError::NetworkError("offline".to_string());
+        (options.on_error.unwrap())(&client, &error, &vars, Some(&context));
+        let real_key = QueryKey::new(["todo", "42"]);
+        assert_eq!(client.get_query_data::<Todo>(&real_key), Some(Todo { id: "42".to_string(), title: "milk".to_string() }));
+        assert!(client.get_cache_entry(&temp_key).is_none());
+    }
+    
+    #[test]
+    fn test_mutate_async_and_reset() {
+        let owner = Owner::new();
+        owner.with(|| {
+            provide_context(QueryClient::new());
+            let mutation = use_mutation(
+                |name: String| async move { Ok::<_, QueryError>(name.len()) },
+                MutationOptions::default(),
+            );
+            
+            let result = futures::executor::block_on(mutation.mutate_async("four".to_string()));
+            assert_eq!(result.unwrap(), 4);
+            assert_eq!(mutation.status.get_untracked(), MutationStatus::Success);
+            assert_eq!(mutation.data.get_untracked(), Some(4));
+            assert_eq!(mutation.variables.get_untracked(), Some("four".to_string()));
+            assert!(mutation.submitted_at.get_untracked().is_some());
+            
+            mutation.reset.run(());
+            assert_eq!(mutation.status.get_untracked(), MutationStatus::Idle);
+            assert_eq!(mutation.data.get_untracked(), None);
+            assert_eq!(mutation.variables.get_untracked(), None);
+        });
+    }
+    
+    #[test]
+    fn test_scoped_mutations_run_in_order() {
+        use futures::channel::oneshot;
+        use futures::executor::LocalPool;
+        use futures::task::LocalSpawnExt;
+        use parking_lot::Mutex;
+        
+        let owner = Owner::new();
+        owner.with(|| {
+            provide_context(QueryClient::new());
+            let (release, blocked) = oneshot::channel::<()>();
+            let blocked = Arc::new(Mutex::new(Some(blocked)));
+            let finished = Arc::new(Mutex::new(Vec::new()));
+            
+            let mutation_fn = {
+                let finished = finished.clone();
+                move |n: u32| {
+                    let blocked = if n == 1 { blocked.lock().take() } else { None };
+                    let finished = finished.clone();
+                    async move {
+                        if let Some(blocked) = blocked {
+                            let _ = blocked.await;
+                        }
+                        finished.lock().push(n);
+                        Ok::<_, QueryError>(n)
+                    }
+                }
+            };
+            let first = use_mutation(mutation_fn.clone(), MutationOptions::default().with_scope("todo"));
+            let second = use_mutation(mutation_fn, MutationOptions::default().with_scope("todo"));
+            
+            let mut pool = LocalPool::new();
+            pool.spawner().spawn_local(first.mutate_async(1).map(|_| ())).unwrap();
+            pool.spawner().spawn_local(second.mutate_async(2).map(|_| ())).unwrap();
+            pool.run_until_stalled();
+            assert_eq!(first.status.get_untracked(), MutationStatus::Pending);
+            assert_eq!(second.status.get_untracked(), MutationStatus::Queued);
+            
+            release.send(()).unwrap();
+            pool.run();
+            assert_eq!(*finished.lock(), vec![1, 2]);
+            assert_eq!(second.status.get_untracked(), MutationStatus::Success);
+        });
+    }
+    
+    #[test]
+    fn test_mutation_cache_tracks_mutations_across_hooks() {
+        use futures::channel::oneshot;
+        use futures::executor::LocalPool;
+        use futures::task::LocalSpawnExt;
+        use parking_lot::Mutex;
+        
+        let owner = Owner::new();
+        owner.with(|| {
+            provide_context(QueryClient::new());
+            let (release, blocked) = oneshot::channel::<()>();
+            let blocked = Arc::new(Mutex::new(Some(blocked)));
+            
+            let save = use_mutation(
+                move |todo: String| {
+                    let blocked = blocked.lock().take();
+                    async move {
+                        if let Some(blocked) = blocked {
+                            let _ = blocked.await;
+                        }
+                        Ok::<_, QueryError>(todo)
+                    }
+                },
+                MutationOptions::default().with_mutation_key(QueryKey::new(["todos", "add"])),
+            );
+            let todos = QueryKeyPattern::Prefix(QueryKey::from("todos"));
+            let is_mutating = use_is_mutating(MutationFilter::new().with_key(todos.clone()));
+            let pending = use_mutation_state(
+                MutationFilter::new().with_key(todos).with_status(MutationStatus::Pending),
+                |mutation| mutation.variables::<String>().cloned(),
+            );
+            
+            let mut pool = LocalPool::new();
+            pool.spawner().spawn_local(save.mutate_async("milk".to_string()).map(|_| ())).unwrap();
+            pool.run_until_stalled();
+            assert_eq!(is_mutating.get_untracked(), 1);
+            assert_eq!(pending.get_untracked(), vec![Some("milk".to_string())]);
+            
+            release.send(()).unwrap();
+            pool.run();
+            assert_eq!(is_mutating.get_untracked(), 0);
+            assert!(pending.get_untracked().is_empty());
+            
+            let client = use_context::<QueryClient>().unwrap();
+            let settled = client.mutation_cache().find_all(&MutationFilter::new());
+            assert_eq!(settled[0].data::<String>(), Some(&"milk".to_string()));
+        });
+    }
+    
+    #[test]
+    fn test_offline_mutation_pauses_until_online() {
+        use crate::sync::NetworkStatus;
+        use futures::executor::LocalPool;
+        use futures::task::LocalSpawnExt;
+        
+        let owner = Owner::new();
+        owner.with(|| {
+            let client = QueryClient::new();
+            client.online_manager().set_status(NetworkStatus::Offline);
+            provide_context(client.clone());
+            
+            let mutation = use_mutation(
+                |n: u32| async move { Ok::<_, QueryError>(n * 2) },
+                MutationOptions::default(),
+            );
+            
+            let mut pool = LocalPool::new();
+            pool.spawner().spawn_local(mutation.mutate_async(21).map(|_| ())).unwrap();
+            pool.run_until_stalled();
+            assert_eq!(mutation.status.get_untracked(), MutationStatus::Paused);
+            assert_eq!(client.mutation_cache().is_mutating(&MutationFilter::new()), 1);
+            
+            client.online_manager().set_status(NetworkStatus::Online);
+            pool.run();
+            assert_eq!(mutation.status.get_untracked(), MutationStatus::Success);
+            assert_eq!(mutation.data.get_untracked(), Some(42));
+        });
+    }
+    
+    #[tokio::test]
+    async fn test_paused_mutation_runs_with_reconciled_variables() {
+        use crate::persistence::{PersistenceConfig, PersistenceManager};
+        use crate::sync::NetworkStatus;
+        use parking_lot::Mutex;
+        
+        #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+        struct Todo {
+            id: String,
+            title: String,
+        }
+        fn rewrite_todo(todo: &mut Todo, temp_id: &str, real_id: &str) -> bool {
+            let matches = todo.id == temp_id;
+            if matches {
+                todo.id = real_id.to_string();
+            }
+            matches
+        }
+        
+        let owner = Owner::new();
+        owner.set();
+        let persistence = Arc::new(PersistenceManager::new(PersistenceConfig::default()).await.unwrap());
+        let client = QueryClient::new().with_persistence(persistence.clone());
+        client.online_manager().set_status(NetworkStatus::Offline);
+        provide_context(client.clone());
+        
+        // An edit of an optimistically created todo, made offline in this session
+        let edit_key = QueryKey::new(["todos", "edit"]);
+        client.set_mutation_defaults(edit_key.clone(), |todo: Todo| async move { Ok::<_, QueryError>(todo) }, MutationOptions::default());
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let edit = use_mutation(
+            {
+                let sent = sent.clone();
+                move |todo: Todo| {
+                    sent.lock().push(todo.id.clone());
+                    async move { Ok::<_, QueryError>(todo) }
+                }
+            },
+            MutationOptions::default().with_mutation_key(edit_key.clone()),
+        );
+        let reconciler = IdReconciler::new().with_variables_rewriter(edit_key, rewrite_todo);
+        
+        // The create confirms while the edit waits for the network
+        let todo = Todo { id: "temp-1".to_string(), title: "milk".to_string() };
+        let (result, _) = futures::join!(edit.mutate_async(todo), async {
+            while persistence.offline_queue().await.unwrap().is_empty() {
+                tokio::task::yield_now().await;
+            }
+            reconciler.reconcile(&client, "temp-1", "42").await;
+            client.online_manager().set_status(NetworkStatus::Online);
+        });
+        
+        assert_eq!(result.ok().map(|todo| todo.id), Some("42".to_string()));
+        assert_eq!(*sent.lock(), vec!["42".to_string()]);
+        assert!(persistence.offline_queue().await.unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_update_queries_with_list_helpers() {
+        #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+        struct Todo {
+            id: u32,
+            title: String,
+        }
+        let todo = |id: u32, title: &str| Todo { id, title: title.to_string() };
+        
+        let client = QueryClient::new();
+        let key = QueryKey::new(["todos", "list"]);
+        client.set_query_data(&key, vec![todo(1, "milk"), todo(2, "eggs")]).unwrap();
+        let todos = QueryKeyPattern::Prefix(QueryKey::from("todos"));
+        
+        let replace = MutationOptions::<Todo>::default().update_queries(todos.clone(), replace_by_id(|todo: &Todo| todo.id));
+        (replace.update_queries[0])(&client, &todo(2, "bread"));
+        let append = MutationOptions::<Todo>::default().update_queries(todos.clone(), append_item::<Todo>());
+        (append.update_queries[0])(&client, &todo(3, "jam"));
+        let remove = MutationOptions::<u32>::default().update_queries(todos, remove_by_id(|todo: &Todo| todo.id, |id: &u32| *id));
+        (remove.update_queries[0])(&client, &1_u32);
+        
+        assert_eq!(client.get_query_data::<Vec<Todo>>(&key), Some(vec![todo(2, "bread"), todo(3, "jam")]));
+    }
+    
+    #[test]
+    fn test_mutation_defaults_to_no_retries() {
+        let options: MutationOptions = MutationOptions::default().with_idempotency_key(|| "key-1".to_string());
+        
+        assert_eq!(options.retry.max_retries, 0);
+        assert_eq!(options.idempotency_key.map(|generate| generate()), Some("key-1".to_string()));
+    }
+    
+    #[tokio::test]
+    async fn test_idempotency_key_is_reused_across_retries() {
+        use parking_lot::Mutex;
+        
+        let owner = Owner::new();
+        owner.set();
+        provide_context(QueryClient::new());
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let counter = Arc::new(AtomicU64::new(0));
+        
+        let seen = keys.clone();
+        let mutation = use_idempotent_mutation(
+            move |n: u32, key: String| {
+                seen.lock().push(key);
+                let failed = seen.lock().len() < 3;
+                async move {
+                    if failed {
+                        Err(QueryError::NetworkError("flaky".to_string()))
+                    } else {
+                        Ok(n)
+                    }
+                }
+            },
+            MutationOptions::default()
+                .with_retry(RetryConfig::new(3, Duration::from_millis(1)))
+                .with_idempotency_key(move || format!("key-{}", counter.fetch_add(1, Ordering::SeqCst))),
+        );
+        
+        assert_eq!(mutation.mutate_async(7).await.ok(), Some(7));
+        assert_eq!(*keys.lock(), vec!["key-0".to_string(); 3]);
+    }
+    
+    #[tokio::test]
+    async fn test_retry_if_limits_retried_errors() {
+        let owner = Owner::new();
+        owner.set();
+        provide_context(QueryClient::new());
+        let attempts = Arc::new(AtomicU64::new(0));
+        
+        let counted = attempts.clone();
+        let mutation = use_mutation(
+            move |_: ()| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async { Err::<(), _>(QueryError::GenericError("409 conflict".to_string())) }
+            },
+            MutationOptions::default()
+                .with_retry(RetryConfig::new(3, Duration::from_millis(1)))
+                .retry_if(|error: &QueryError| !error.to_string().contains("409")),
+        );
+        
+        assert!(mutation.mutate_async(()).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
     }
 }
\ No newline at end of file
@@ -1,5 +1,8 @@
 use crate::types::{QueryKey, QueryKeyPattern};
 use crate::retry::QueryError;
+use crate::client::{CacheEntry, QueryClient};
+use crate::mutation::PausedMutation;
+use crate::persistence::{OfflineRequestType, PersistenceManager};
 use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
@@ -145,6 +148,11 @@ impl<T: Clone + Serialize + DeserializeOwned> OptimisticManager<T> {
             .map(|update| update.data.clone())
     }
 
+    /// Get an optimistic update by ID
+    pub fn get_update(&self, update_id: &str) -> Option<OptimisticUpdate<T>> {
+        self.updates.read().get(update_id).cloned()
+    }
+
     /// Confirm an optimistic update
     pub fn confirm_update(&self, update_id: &str) -> Result<(), QueryError> {
         let mut updates = self.updates.write();
@@ -203,6 +211,42 @@ impl<T: Clone + Serialize + DeserializeOwned> OptimisticManager<T> {
         rollback_data
     }
 
+    /// Rewrite a temporary ID to the real one in the keys and data of active updates
+    ///
+    /// `rewrite` replaces the ID inside one value and returns whether it changed anything.
+    /// Returns the number of updates that changed.
+    pub fn remap_id(&self, temp_id: &str, real_id: &str, rewrite: impl Fn(&mut T, &str, &str) -> bool) -> usize {
+        self.remap_id_by_key(temp_id, real_id, |_, data, temp_id, real_id| rewrite(data, temp_id, real_id))
+    }
+
+    /// Like [`OptimisticManager::remap_id`], passing `rewrite` the already remapped key of each update
+    pub(crate) fn remap_id_by_key(
+        &self,
+        temp_id: &str,
+        real_id: &str,
+        rewrite: impl Fn(&QueryKey, &mut T, &str, &str) -> bool,
+    ) -> usize {
+        let mut updates = self.updates.write();
+        let mut changed = 0;
+        
+        for update in updates.values_mut().filter(|update| !update.confirmed && !update.rolled_back) {
+            let mut update_changed = false;
+            if let Some(key) = update.key.replace_segment(temp_id, real_id) {
+                update.key = key;
+                update_changed = true;
+            }
+            update_changed |= rewrite(&update.key, &mut update.data, temp_id, real_id);
+            if let Some(rollback_data) = &mut update.rollback_data {
+                update_changed |= rewrite(&update.key, rollback_data, temp_id, real_id);
+            }
+            if update_changed {
+                changed += 1;
+            }
+        }
+        
+        changed
+    }
+
     /// Clean up expired updates
     pub fn cleanup_expired(&self) -> usize {
         let mut updates = self.updates.write();
@@ -255,6 +299,186 @@ impl<T: Clone + Serialize + DeserializeOwned> OptimisticManager<T> {
     }
 }
 
+/// Prefix of generated temporary IDs
+const TEMP_ID_PREFIX: &str = "temp-";
+
+/// Generate a temporary ID for an optimistically created item
+pub fn generate_temp_id() -> String {
+    format!("{}{}", TEMP_ID_PREFIX, uuid::Uuid::new_v4())
+}
+
+/// Check if an ID was generated by [`generate_temp_id`]
+pub fn is_temp_id(id: &str) -> bool {
+    id.starts_with(TEMP_ID_PREFIX)
+}
+
+/// Rewrites a temporary ID in cached queries, returning the number of queries changed
+type QueryRewriteFn = Arc<dyn Fn(&QueryClient, &str, &str) -> usize + Send + Sync>;
+
+/// Rewrites a temporary ID in the data of a cache entry snapshot, returning whether it changed anything
+type SnapshotRewriteFn = Arc<dyn Fn(&mut CacheEntry, &str, &str) -> bool + Send + Sync>;
+
+/// Rewrites a temporary ID in serialized mutation variables, returning the new bytes if changed
+type VariablesRewriteFn = Arc<dyn Fn(&[u8], &str, &str) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Records temporary to real ID mappings for optimistic creates and rewrites references to them
+///
+/// Mutations queued behind a create should look their IDs up through [`IdReconciler::resolve`]
+/// when they run, so they pick up the real ID once the create confirms.
+#[derive(Clone, Default)]
+pub struct IdReconciler {
+    mappings: Arc<RwLock<HashMap<String, String>>>,
+    query_rewriters: Arc<RwLock<Vec<QueryRewriteFn>>>,
+    snapshot_rewriters: Arc<RwLock<Vec<(QueryKeyPattern, SnapshotRewriteFn)>>>,
+    variables_rewriters: Arc<RwLock<HashMap<QueryKey, VariablesRewriteFn>>>,
+}
+
+impl IdReconciler {
+    /// Create a new reconciler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rewrite IDs inside the data of cached queries matching a pattern
+    ///
+    /// `rewrite` replaces the ID inside one value and returns whether it changed anything.
+    pub fn with_query_rewriter<T>(
+        self,
+        pattern: QueryKeyPattern,
+        rewrite: impl Fn(&mut T, &str, &str) -> bool + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        let rewrite = Arc::new(rewrite);
+        self.query_rewriters.write().push(Arc::new({
+            let pattern = pattern.clone();
+            let rewrite = rewrite.clone();
+            move |client, temp_id, real_id| {
+                client.set_queries_data::<T>(&pattern, |mut data| {
+                    rewrite(&mut data, temp_id, real_id).then_some(data)
+                })
+            }
+        }));
+        self.snapshot_rewriters.write().push((pattern, Arc::new(move |entry, temp_id, real_id| {
+            if entry.is_infinite() {
+                return false;
+            }
+            let Ok(mut data) = entry.get_data::<T>() else { return false };
+            if !rewrite(&mut data, temp_id, real_id) {
+                return false;
+            }
+            let Ok(bytes) = bincode::serialize(&data) else { return false };
+            entry.data.data = bytes;
+            true
+        })));
+        self
+    }
+
+    /// Rewrite IDs inside the variables of persisted paused mutations with a mutation key
+    pub fn with_variables_rewriter<TVariables>(
+        self,
+        mutation_key: QueryKey,
+        rewrite: impl Fn(&mut TVariables, &str, &str) -> bool + Send + Sync + 'static,
+    ) -> Self
+    where
+        TVariables: Serialize + DeserializeOwned + 'static,
+    {
+        self.variables_rewriters.write().insert(mutation_key, Arc::new(move |variables, temp_id, real_id| {
+            let mut variables = bincode::deserialize::<TVariables>(variables).ok()?;
+            if !rewrite(&mut variables, temp_id, real_id) {
+                return None;
+            }
+            bincode::serialize(&variables).ok()
+        }));
+        self
+    }
+
+    /// Get the real ID recorded for a temporary ID
+    pub fn real_id(&self, temp_id: &str) -> Option<String> {
+        self.mappings.read().get(temp_id).cloned()
+    }
+
+    /// Get the real ID for an ID, or the ID itself if it isn't a mapped temporary ID
+    pub fn resolve(&self, id: &str) -> String {
+        self.real_id(id).unwrap_or_else(|| id.to_string())
+    }
+
+    /// Get all recorded mappings from temporary to real IDs
+    pub fn mappings(&self) -> HashMap<String, String> {
+        self.mappings.read().clone()
+    }
+
+    /// Record that a temporary ID became a real one and rewrite the references to it
+    ///
+    /// Query keys containing the temporary ID are moved to the real ID, query data is rewritten
+    /// through the registered query rewriters, and persisted paused mutations through the
+    /// registered variables rewriters. Pending optimistic updates of the client get the same
+    /// key and data rewrites, so rolling one back restores a snapshot with the real ID.
+    /// Returns the number of cache entries, optimistic updates and queued mutations that changed.
+    pub async fn reconcile(&self, client: &QueryClient, temp_id: &str, real_id: &str) -> usize {
+        self.mappings.write().insert(temp_id.to_string(), real_id.to_string());
+        
+        let mut changed = client.replace_key_segment(temp_id, real_id);
+        let query_rewriters = self.query_rewriters.read().clone();
+        for rewrite in query_rewriters {
+            changed += rewrite(client, temp_id, real_id);
+        }
+        let snapshot_rewriters = self.snapshot_rewriters.read().clone();
+        changed += client.optimistic_updates().remap_id_by_key(temp_id, real_id, |key, entry, temp_id, real_id| {
+            snapshot_rewriters
+                .iter()
+                .filter(|(pattern, _)| key.matches_pattern(pattern))
+                .fold(false, |changed, (_, rewrite)| rewrite(entry, temp_id, real_id) | changed)
+        });
+        
+        if let Some(persistence) = client.persistence() {
+            changed += self.reconcile_offline_queue(persistence, temp_id, real_id).await;
+        }
+        
+        changed
+    }
+
+    /// Rewrite a temporary ID in the active updates of an optimistic manager
+    pub fn reconcile_optimistic<T>(
+        &self,
+        manager: &OptimisticManager<T>,
+        temp_id: &str,
+        real_id: &str,
+        rewrite: impl Fn(&mut T, &str, &str) -> bool,
+    ) -> usize
+    where
+        T: Clone + Serialize + DeserializeOwned,
+    {
+        manager.remap_id(temp_id, real_id, rewrite)
+    }
+
+    /// Rewrite a temporary ID in persisted paused mutations
+    async fn reconcile_offline_queue(&self, persistence: &PersistenceManager, temp_id: &str, real_id: &str) -> usize {
+        let Ok(queue) = persistence.offline_queue().await else { return 0 };
+        let mut changed = 0;
+        
+        for (id, mut request) in queue {
+            if !matches!(request.request_type, OfflineRequestType::Mutation) {
+                continue;
+            }
+            let Ok(mut paused) = bincode::deserialize::<PausedMutation>(&request.data) else { continue };
+            
+            let rewriter = self.variables_rewriters.read().get(&paused.mutation_key).cloned();
+            let Some(variables) = rewriter.and_then(|rewrite| rewrite(&paused.variables, temp_id, real_id)) else { continue };
+            paused.variables = variables;
+            
+            let Ok(data) = bincode::serialize(&paused) else { continue };
+            request.data = data;
+            if persistence.update_offline_request(&id, &request).await.is_ok() {
+                changed += 1;
+            }
+        }
+        
+        changed
+    }
+}
+
 /// Statistics for optimistic updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimisticStats {
@@ -378,4 +602,64 @@ mod tests {
         // Verify no optimistic data remains
         assert_eq!(manager.get_optimistic_data(&key), None);
     }
+    
+    #[tokio::test]
+    async fn test_id_reconciliation() {
+        use crate::mutation::{MutationOptions, PausedMutation};
+        use crate::persistence::PersistenceConfig;
+        
+        #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+        struct Todo {
+            id: String,
+            title: String,
+        }
+        fn rewrite_todo(todo: &mut Todo, temp_id: &str, real_id: &str) -> bool {
+            if todo.id == temp_id {
+                todo.id = real_id.to_string();
+                true
+            } else {
+                false
+            }
+        }
+        
+        let persistence = Arc::new(PersistenceManager::new(PersistenceConfig::default()).await.unwrap());
+        let client = QueryClient::new().with_persistence(persistence.clone());
+        let temp_id = generate_temp_id();
+        assert!(is_temp_id(&temp_id));
+        
+        let todo = Todo { id: temp_id.clone(), title: "milk".to_string() };
+        client.set_query_data(&QueryKey::new(["todo", temp_id.as_str()]), todo.clone()).unwrap();
+        client.set_query_data(&QueryKey::new(["todos"]), vec![todo.clone()]).unwrap();
+        
+        // A follow-up edit made offline before the create confirmed
+        let edit_key = QueryKey::new(["todos", "edit"]);
+        client.set_mutation_defaults(edit_key.clone(), |todo: Todo| async move { Ok::<_, QueryError>(todo) }, MutationOptions::default());
+        client.persist_paused_mutation(&edit_key, &todo).await.unwrap();
+        
+        let manager = OptimisticManager::<Todo>::new(OptimisticConfig::default());
+        manager.apply_update(&QueryKey::new(["todo", temp_id.as_str()]), todo.clone(), None);
+        
+        let reconciler = IdReconciler::new()
+            .with_query_rewriter(QueryKeyPattern::Prefix(QueryKey::from("todo")), rewrite_todo)
+            .with_query_rewriter(QueryKeyPattern::Exact(QueryKey::new(["todos"])), |todos: &mut Vec<Todo>, temp_id, real_id| {
+                let mut changed = false;
+                for todo in todos.iter_mut() {
+                    changed |= rewrite_todo(todo, temp_id, real_id);
+                }
+                changed
+            })
+            .with_variables_rewriter(edit_key, rewrite_todo);
+        assert_eq!(reconciler.reconcile(&client, &temp_id, "42").await, 4);
+        assert_eq!(reconciler.reconcile_optimistic(&manager, &temp_id, "42", rewrite_todo), 1);
+        
+        let real = Todo { id: "42".to_string(), title: "milk".to_string() };
+        assert_eq!(reconciler.resolve(&temp_id), "42");
+        assert_eq!(client.get_query_data::<Todo>(&QueryKey::new(["todo", "42"])), Some(real.clone()));
+        assert_eq!(client.get_query_data::<Vec<Todo>>(&QueryKey::new(["todos"])), Some(vec![real.clone()]));
+        assert_eq!(manager.get_optimistic_data(&QueryKey::new(["todo", "42"])), Some(real.clone()));
+        
+        let (_, request) = persistence.offline_queue().await.unwrap().remove(0);
+        let paused: PausedMutation = bincode::deserialize(&request.data).unwrap();
+        assert_eq!(bincode::deserialize::<Todo>(&paused.variables).unwrap(), real);
+    }
 }
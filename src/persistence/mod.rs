@@ -8,6 +8,14 @@ use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
 use web_sys::Storage;
 
+/// Header stored in front of persisted cache entries
+///
+/// Bump the version in the low bytes whenever the layout of a cache entry or of the data cached
+/// in it changes, for example when a field is added to `PageInfo`; bincode can't read entries
+/// written with another layout. The high bytes keep the header apart from entries stored without
+/// one, which start with the length of their data.
+const CACHE_ENTRY_FORMAT: u64 = 0x4c51_5243_0000_0001;
+
 /// Trait for storage backends
 #[async_trait]
 pub trait StorageBackend: Send + Sync {
@@ -413,7 +421,7 @@ impl PersistenceManager {
     
     /// Store a cache entry
     pub async fn store_cache_entry(&self, key: &crate::types::QueryKey, entry: &crate::client::CacheEntry) -> Result<(), QueryError> {
-        let data = bincode::serialize(entry)
+        let data = bincode::serialize(&(CACHE_ENTRY_FORMAT, entry))
             .map_err(|e| QueryError::StorageError(format!("Serialization failed: {}", e)))?;
         
         let key_str = key.to_string();
@@ -421,10 +429,15 @@ impl PersistenceManager {
     }
     
     /// Retrieve a cache entry
+    ///
+    /// Entries stored in an older format are treated as missing, so their queries fetch again.
     pub async fn retrieve_cache_entry(&self, key: &crate::types::QueryKey) -> Result<Option<crate::client::CacheEntry>, QueryError> {
         let key_str = key.to_string();
         if let Some(data) = self.backend.retrieve(&key_str).await? {
-            let entry: crate::client::CacheEntry = bincode::deserialize(&data)
+            if bincode::deserialize::<u64>(&data).ok() != Some(CACHE_ENTRY_FORMAT) {
+                return Ok(None);
+            }
+            let (_, entry): (u64, crate::client::CacheEntry) = bincode::deserialize(&data)
                 .map_err(|e| QueryError::StorageError(format!("Deserialization failed: {}", e)))?;
             Ok(Some(entry))
         } else {
@@ -467,17 +480,18 @@ impl PersistenceManager {
         })
     }
     
-    /// Add a request to the offline queue
-    pub async fn add_to_offline_queue(&self, request: OfflineRequest) -> Result<(), QueryError> {
+    /// Add a request to the offline queue, returning its queue ID
+    pub async fn add_to_offline_queue(&self, request: OfflineRequest) -> Result<String, QueryError> {
         let data = bincode::serialize(&request)
             .map_err(|e| QueryError::StorageError(format!("Serialization failed: {}", e)))?;
         
-        let key = format!("offline_queue_{}", request.timestamp.elapsed().as_millis());
-        self.backend.store(&key, &data).await
+        let key = format!("offline_queue_{}", uuid::Uuid::new_v4());
+        self.backend.store(&key, &data).await?;
+        Ok(key)
     }
     
-    /// Process the offline queue
-    pub async fn process_offline_queue(&self) -> Result<Vec<OfflineRequest>, QueryError> {
+    /// Get the queued requests with their queue IDs, oldest first
+    pub async fn offline_queue(&self) -> Result<Vec<(String, OfflineRequest)>, QueryError> {
         let keys = self.backend.list_keys().await?;
         let mut requests = Vec::new();
         
@@ -485,14 +499,39 @@ impl PersistenceManager {
             if key.starts_with("offline_queue_") {
                 if let Some(data) = self.backend.retrieve(&key).await? {
                     if let Ok(request) = bincode::deserialize::<OfflineRequest>(&data) {
-                        requests.push(request);
+                        requests.push((key, request));
                     }
                 }
-                // Remove the processed request
-                let _ = self.backend.remove(&key).await;
             }
         }
         
+        requests.sort_by_key(|(_, request)| request.timestamp);
+        Ok(requests)
+    }
+    
+    /// Replace a request in the offline queue, keeping its place
+    pub async fn update_offline_request(&self, id: &str, request: &OfflineRequest) -> Result<(), QueryError> {
+        let data = bincode::serialize(request)
+            .map_err(|e| QueryError::StorageError(format!("Serialization failed: {}", e)))?;
+        
+        self.backend.store(id, &data).await
+    }
+    
+    /// Remove a request from the offline queue
+    pub async fn remove_from_offline_queue(&self, id: &str) -> Result<(), QueryError> {
+        self.backend.remove(id).await
+    }
+    
+    /// Process the offline queue
+    pub async fn process_offline_queue(&self) -> Result<Vec<OfflineRequest>, QueryError> {
+        let mut requests = Vec::new();
+        
+        for (id, request) in self.offline_queue().await? {
+            requests.push(request);
+            // Remove the processed request
+            let _ = self.backend.remove(&id).await;
+        }
+        
         Ok(requests)
     }
 
@@ -606,6 +645,23 @@ mod tests {
         assert_eq!(keys.len(), 0);
     }
     
+    #[tokio::test]
+    async fn test_cache_entries_from_older_formats_are_ignored() {
+        let manager = PersistenceManager::new(PersistenceConfig::default()).await.unwrap();
+        let client = crate::client::QueryClient::new();
+        let key = crate::types::QueryKey::from("todos");
+        client.set_query_data(&key, vec![1_u32, 2]).unwrap();
+        let entry = client.get_cache_entry(&key).unwrap();
+        
+        manager.store_cache_entry(&key, &entry).await.unwrap();
+        let restored = manager.retrieve_cache_entry(&key).await.unwrap().unwrap();
+        assert_eq!(restored.get_data::<Vec<u32>>().unwrap(), vec![1, 2]);
+        
+        // An entry written before the format header existed
+        manager.backend.store(&key.to_string(), &bincode::serialize(&entry).unwrap()).await.unwrap();
+        assert!(manager.retrieve_cache_entry(&key).await.unwrap().is_none());
+    }
+    
     #[tokio::test]
     async fn test_persistence_manager() {
         let config = PersistenceConfig::default();
@@ -4,15 +4,21 @@
 
 use leptos::prelude::*;
 use leptos::task::spawn_local;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::future::Future;
+use std::sync::Arc;
+use futures::future::{FutureExt, LocalBoxFuture};
 use serde::{Serialize, de::DeserializeOwned};
 
 use crate::client::QueryClient;
-use crate::retry::{QueryError, RetryConfig, execute_with_retry};
+use crate::retry::{QueryError, RetryConfig, execute_with_retry_and_timeout, sleep, with_hedge};
+use crate::scheduler::FetchPriority;
 use crate::types::{QueryStatus, QueryKey};
 
 /// Options for configuring a query
+///
+/// Options set through the builder methods are never replaced by client defaults, even
+/// when they are set to their default value.
 #[derive(Clone)]
 pub struct QueryOptions {
     /// Whether the query should run
@@ -25,6 +31,41 @@ pub struct QueryOptions {
     pub refetch_interval: Option<Duration>,
     /// Retry configuration
     pub retry: RetryConfig,
+    /// Keep serving cached data, alongside the error, when a refetch fails
+    pub stale_if_error: bool,
+    /// How old cached data may be and still be served after a failed refetch
+    pub max_stale_if_error: Option<Duration>,
+    /// Maximum duration of a single fetch attempt
+    pub timeout: Option<Duration>,
+    /// Latency after which an identical hedge request is sent
+    pub hedge_after: Option<Duration>,
+    /// Priority of the query's fetches in the client's fetch queue
+    pub priority: FetchPriority,
+    /// Wait for the key to stop changing this long before fetching for it
+    pub debounce: Option<Duration>,
+    /// Fetch for a changed key at most once per this interval
+    pub throttle: Option<Duration>,
+    /// Options set through the builder methods
+    ///
+    /// Struct literals leave it at its default, so options set there count as set only when
+    /// they differ from [`QueryOptions::default`].
+    pub explicit: ExplicitOptions,
+}
+
+/// Options a query set through its builder methods, which client defaults never replace
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExplicitOptions {
+    stale_time: bool,
+    cache_time: bool,
+    refetch_interval: bool,
+    retry: bool,
+    stale_if_error: bool,
+    max_stale_if_error: bool,
+    timeout: bool,
+    hedge_after: bool,
+    priority: bool,
+    debounce: bool,
+    throttle: bool,
 }
 
 impl Default for QueryOptions {
@@ -35,6 +76,14 @@ impl Default for QueryOptions {
             cache_time: Duration::from_secs(5 * 60), // 5 minutes
             refetch_interval: None,
             retry: RetryConfig::default(),
+            stale_if_error: false,
+            max_stale_if_error: None,
+            timeout: None,
+            hedge_after: None,
+            priority: FetchPriority::Normal,
+            debounce: None,
+            throttle: None,
+            explicit: ExplicitOptions::default(),
         }
     }
 }
@@ -43,24 +92,117 @@ impl QueryOptions {
     /// Create options with custom stale time
     pub fn with_stale_time(mut self, duration: Duration) -> Self {
         self.stale_time = duration;
+        self.explicit.stale_time = true;
         self
     }
     
     /// Create options with custom cache time
     pub fn with_cache_time(mut self, duration: Duration) -> Self {
         self.cache_time = duration;
+        self.explicit.cache_time = true;
         self
     }
     
     /// Create options with refetch interval
     pub fn with_refetch_interval(mut self, interval: Duration) -> Self {
         self.refetch_interval = Some(interval);
+        self.explicit.refetch_interval = true;
         self
     }
     
     /// Create options with retry configuration
     pub fn with_retry(mut self, retry: RetryConfig) -> Self {
         self.retry = retry;
+        self.explicit.retry = true;
+        self
+    }
+    
+    /// Keep serving cached data when a refetch fails
+    pub fn with_stale_if_error(mut self) -> Self {
+        self.stale_if_error = true;
+        self.explicit.stale_if_error = true;
+        self
+    }
+    
+    /// Keep serving cached data no older than `max_stale` when a refetch fails
+    pub fn with_max_stale_if_error(mut self, max_stale: Duration) -> Self {
+        self.stale_if_error = true;
+        self.max_stale_if_error = Some(max_stale);
+        self.explicit.stale_if_error = true;
+        self.explicit.max_stale_if_error = true;
+        self
+    }
+    
+    /// Report failed refetches as errors, even if the client serves stale data by default
+    pub fn without_stale_if_error(mut self) -> Self {
+        self.stale_if_error = false;
+        self.max_stale_if_error = None;
+        self.explicit.stale_if_error = true;
+        self.explicit.max_stale_if_error = true;
+        self
+    }
+    
+    /// Create options with a per-attempt timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.explicit.timeout = true;
+        self
+    }
+    
+    /// Let fetch attempts run as long as they take, even if the client sets a default timeout
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = None;
+        self.explicit.timeout = true;
+        self
+    }
+    
+    /// Send a hedge request when a fetch hasn't answered within `latency`
+    ///
+    /// Only use this for read-only queries.
+    pub fn with_hedge_after(mut self, latency: Duration) -> Self {
+        self.hedge_after = Some(latency);
+        self.explicit.hedge_after = true;
+        self
+    }
+    
+    /// Never send hedge requests, even if the client sets a default hedge latency
+    pub fn without_hedge(mut self) -> Self {
+        self.hedge_after = None;
+        self.explicit.hedge_after = true;
+        self
+    }
+    
+    /// Create options with a fetch priority
+    pub fn with_priority(mut self, priority: FetchPriority) -> Self {
+        self.priority = priority;
+        self.explicit.priority = true;
+        self
+    }
+    
+    /// Only react to key changes once the key has been stable for `wait`
+    ///
+    /// The previous data stays visible while waiting. Takes precedence over a throttle.
+    pub fn with_debounce(mut self, wait: Duration) -> Self {
+        self.debounce = Some(wait);
+        self.explicit.debounce = true;
+        self
+    }
+    
+    /// React to key changes at most once per `interval`, always ending on the latest key
+    ///
+    /// The previous data stays visible while waiting.
+    pub fn with_throttle(mut self, interval: Duration) -> Self {
+        self.throttle = Some(interval);
+        self.explicit.throttle = true;
+        self
+    }
+    
+    /// React to key changes right away, even if the client sets a default debounce or throttle
+    pub fn without_key_delay(mut self) -> Self {
+        self.debounce = None;
+        self.throttle = None;
+        self.explicit.debounce = true;
+        self.explicit.throttle = true;
         self
     }
     
@@ -71,6 +213,294 @@ impl QueryOptions {
     }
 }
 
+/// Partial query options, filled in for the options a query doesn't set itself
+///
+/// Set on a [`QueryClientConfig`](crate::client::QueryClientConfig) for every query, or
+/// with [`QueryClient::set_query_defaults`] for queries matching a key pattern.
+#[derive(Clone, Debug, Default)]
+pub struct QueryDefaults {
+    /// Time before data becomes stale
+    pub stale_time: Option<Duration>,
+    /// Time before data is removed from cache
+    pub cache_time: Option<Duration>,
+    /// Interval for background refetching
+    pub refetch_interval: Option<Duration>,
+    /// Retry configuration
+    pub retry: Option<RetryConfig>,
+    /// Keep serving cached data, alongside the error, when a refetch fails
+    pub stale_if_error: Option<bool>,
+    /// How old cached data may be and still be served after a failed refetch
+    pub max_stale_if_error: Option<Duration>,
+    /// Maximum duration of a single fetch attempt
+    pub timeout: Option<Duration>,
+    /// Latency after which an identical hedge request is sent
+    pub hedge_after: Option<Duration>,
+    /// Priority of fetches in the client's fetch queue
+    pub priority: Option<FetchPriority>,
+    /// Wait for the key to stop changing this long before fetching for it
+    pub debounce: Option<Duration>,
+    /// Fetch for a changed key at most once per this interval
+    pub throttle: Option<Duration>,
+}
+
+impl QueryDefaults {
+    /// Create defaults that leave every option alone
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Default stale time
+    pub fn with_stale_time(mut self, duration: Duration) -> Self {
+        self.stale_time = Some(duration);
+        self
+    }
+    
+    /// Default cache time
+    pub fn with_cache_time(mut self, duration: Duration) -> Self {
+        self.cache_time = Some(duration);
+        self
+    }
+    
+    /// Default refetch interval
+    pub fn with_refetch_interval(mut self, interval: Duration) -> Self {
+        self.refetch_interval = Some(interval);
+        self
+    }
+    
+    /// Default retry configuration
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+    
+    /// Serve cached data when a refetch fails by default
+    pub fn with_stale_if_error(mut self) -> Self {
+        self.stale_if_error = Some(true);
+        self
+    }
+    
+    /// Serve cached data no older than `max_stale` when a refetch fails by default
+    pub fn with_max_stale_if_error(mut self, max_stale: Duration) -> Self {
+        self.stale_if_error = Some(true);
+        self.max_stale_if_error = Some(max_stale);
+        self
+    }
+    
+    /// Default per-attempt timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    
+    /// Default latency after which a hedge request is sent
+    ///
+    /// Only use this for read-only queries.
+    pub fn with_hedge_after(mut self, latency: Duration) -> Self {
+        self.hedge_after = Some(latency);
+        self
+    }
+    
+    /// Default fetch priority
+    pub fn with_priority(mut self, priority: FetchPriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+    
+    /// Default debounce of key changes
+    pub fn with_debounce(mut self, wait: Duration) -> Self {
+        self.debounce = Some(wait);
+        self
+    }
+    
+    /// Default throttle of key changes
+    pub fn with_throttle(mut self, interval: Duration) -> Self {
+        self.throttle = Some(interval);
+        self
+    }
+    
+    /// Get these defaults with the fields set in `other` taking precedence
+    pub fn merge(&self, other: &QueryDefaults) -> Self {
+        Self {
+            stale_time: other.stale_time.or(self.stale_time),
+            cache_time: other.cache_time.or(self.cache_time),
+            refetch_interval: other.refetch_interval.or(self.refetch_interval),
+            retry: other.retry.clone().or_else(|| self.retry.clone()),
+            stale_if_error: other.stale_if_error.or(self.stale_if_error),
+            max_stale_if_error: other.max_stale_if_error.or(self.max_stale_if_error),
+            timeout: other.timeout.or(self.timeout),
+            hedge_after: other.hedge_after.or(self.hedge_after),
+            priority: other.priority.or(self.priority),
+            debounce: other.debounce.or(self.debounce),
+            throttle: other.throttle.or(self.throttle),
+        }
+    }
+    
+    /// Fill in the options a query doesn't set itself
+    ///
+    /// An option counts as set if a builder method set it or it differs from [`QueryOptions::default`].
+    pub fn apply_to(&self, mut options: QueryOptions) -> QueryOptions {
+        let base = QueryOptions::default();
+        let explicit = options.explicit;
+        
+        fill(&mut options.stale_time, explicit.stale_time, base.stale_time, self.stale_time);
+        fill(&mut options.cache_time, explicit.cache_time, base.cache_time, self.cache_time);
+        fill(&mut options.refetch_interval, explicit.refetch_interval, None, self.refetch_interval.map(Some));
+        fill(&mut options.retry, explicit.retry, base.retry, self.retry.clone());
+        fill(&mut options.stale_if_error, explicit.stale_if_error, base.stale_if_error, self.stale_if_error);
+        fill(&mut options.max_stale_if_error, explicit.max_stale_if_error, None, self.max_stale_if_error.map(Some));
+        fill(&mut options.timeout, explicit.timeout, None, self.timeout.map(Some));
+        fill(&mut options.hedge_after, explicit.hedge_after, None, self.hedge_after.map(Some));
+        fill(&mut options.priority, explicit.priority, base.priority, self.priority);
+        fill(&mut options.debounce, explicit.debounce, None, self.debounce.map(Some));
+        fill(&mut options.throttle, explicit.throttle, None, self.throttle.map(Some));
+        options
+    }
+}
+
+/// Replace an option with its default, unless it was set explicitly or differs from `unset`
+fn fill<T: PartialEq>(value: &mut T, explicit: bool, unset: T, default: Option<T>) {
+    if let Some(default) = default.filter(|_| !explicit && *value == unset) {
+        *value = default;
+    }
+}
+
+/// How long a query waits before reacting to a key change
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KeyDelay {
+    Debounce(Duration),
+    Throttle(Duration),
+}
+
+impl KeyDelay {
+    fn from_options(options: &QueryOptions) -> Option<Self> {
+        options
+            .debounce
+            .map(Self::Debounce)
+            .or(options.throttle.map(Self::Throttle))
+    }
+    
+    /// Get the wait for a key change made at `now`, given when a key was last applied
+    fn wait(&self, now: Instant, last_applied: Option<Instant>) -> Duration {
+        match *self {
+            Self::Debounce(wait) => wait,
+            Self::Throttle(interval) => last_applied
+                .map_or(Duration::ZERO, |last| interval.saturating_sub(now.duration_since(last))),
+        }
+    }
+}
+
+/// Get the key a query fetches for, following `key` after the delay `delay_for` gives the new key
+///
+/// A key change still waiting is dropped when a newer one comes in, including a change back
+/// to the key already applied.
+fn delayed_key(
+    key: Memo<QueryKey>,
+    delay_for: impl Fn(&QueryKey) -> Option<KeyDelay> + 'static,
+) -> Signal<QueryKey> {
+    let applied = RwSignal::new(key.get_untracked());
+    let last_applied = StoredValue::new(None::<Instant>);
+    let generation = StoredValue::new(0u64);
+    
+    Effect::new(move |_| {
+        let current = key.get();
+        generation.update_value(|generation| *generation += 1);
+        if applied.with_untracked(|applied| *applied == current) {
+            return;
+        }
+        
+        let Some(delay) = delay_for(&current) else {
+            last_applied.set_value(Some(Instant::now()));
+            applied.set(current);
+            return;
+        };
+        let change = generation.get_value();
+        let wait = delay.wait(Instant::now(), last_applied.get_value());
+        spawn_local(async move {
+            if !wait.is_zero() {
+                sleep(wait).await;
+            }
+            // Superseded by a newer key change
+            if generation.try_get_value() != Some(change) {
+                return;
+            }
+            last_applied.set_value(Some(Instant::now()));
+            applied.set(current);
+        });
+    });
+    
+    applied.into()
+}
+
+type SourceFn<T> = Arc<dyn Fn() -> LocalBoxFuture<'static, Result<T, QueryError>> + Send + Sync>;
+
+/// A named data source in a fallback chain
+#[derive(Clone)]
+pub struct QuerySource<T> {
+    /// Name recorded when this source serves the data
+    pub name: String,
+    /// Retry configuration for this source
+    pub retry: RetryConfig,
+    fetcher: SourceFn<T>,
+}
+
+/// Ordered list of data sources, tried until one of them succeeds
+#[derive(Clone)]
+pub struct QuerySources<T> {
+    sources: Vec<QuerySource<T>>,
+}
+
+impl<T: 'static> QuerySources<T> {
+    /// Create an empty source chain
+    pub fn new() -> Self {
+        Self { sources: Vec::new() }
+    }
+    
+    /// Append a source to the chain
+    pub fn with_source<F, Fut>(mut self, name: impl Into<String>, fetcher: F, retry: RetryConfig) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, QueryError>> + 'static,
+    {
+        self.sources.push(QuerySource {
+            name: name.into(),
+            retry,
+            fetcher: Arc::new(move || fetcher().boxed_local()),
+        });
+        self
+    }
+    
+    /// Get the sources in the order they are tried
+    pub fn sources(&self) -> &[QuerySource<T>] {
+        &self.sources
+    }
+    
+    /// Fetch from each source in turn, returning the data and the name of the source that served it
+    pub async fn fetch(&self, options: &QueryOptions) -> Result<(T, String), QueryError> {
+        let mut last_error = None;
+        
+        for source in &self.sources {
+            let result = execute_with_retry_and_timeout(
+                || with_hedge(|| (source.fetcher)(), options.hedge_after),
+                &source.retry,
+                options.timeout,
+            ).await;
+            
+            match result {
+                Ok(data) => return Ok((data, source.name.clone())),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        
+        Err(last_error.unwrap_or_else(|| QueryError::GenericError("No query sources configured".to_string())))
+    }
+}
+
+impl<T: 'static> Default for QuerySources<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Result of a query hook
 #[derive(Clone)]
 pub struct QueryResult<T: 'static + Send + Sync> {
@@ -86,6 +516,10 @@ pub struct QueryResult<T: 'static + Send + Sync> {
     pub is_error: Signal<bool>,
     /// Current query status
     pub status: Signal<QueryStatus>,
+    /// Whether the data is stale because the last refetch failed
+    pub is_refetch_error: Signal<bool>,
+    /// Name of the source that served the data
+    pub source: Signal<Option<String>>,
     
     // Actions
     /// Refetch the query
@@ -102,32 +536,75 @@ where
     T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
     F: Fn() -> QueryKey + Clone + Send + Sync + 'static,
     Fut: Future<Output = Result<T, QueryError>> + 'static,
+{
+    // The primary source retries with the resolved options of the key being fetched
+    let sources_for = move |options: &QueryOptions| {
+        QuerySources::new().with_source("primary", query_fn.clone(), options.retry.clone())
+    };
+    query_hook(key_fn, sources_for, options)
+}
+
+/// Query hook that falls back to the next source when a source fails
+pub fn use_query_with_fallback<T, F>(
+    key_fn: F,
+    sources: QuerySources<T>,
+    options: QueryOptions,
+) -> QueryResult<T>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    F: Fn() -> QueryKey + Clone + Send + Sync + 'static,
+{
+    query_hook(key_fn, move |_: &QueryOptions| sources.clone(), options)
+}
+
+/// Shared implementation of the query hooks
+///
+/// Options are resolved against the client's defaults for each key that is fetched,
+/// and `sources_for` builds the sources from the resolved options.
+fn query_hook<T, F>(
+    key_fn: F,
+    sources_for: impl Fn(&QueryOptions) -> QuerySources<T> + Clone + Send + Sync + 'static,
+    options: QueryOptions,
+) -> QueryResult<T>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    F: Fn() -> QueryKey + Clone + Send + Sync + 'static,
 {
     // Create reactive state
     let (data, set_data) = signal(None::<T>);
     let (error, set_error) = signal(None::<QueryError>);
     let (is_loading, set_loading) = signal(true);
     let (status, set_status) = signal(QueryStatus::Loading);
+    let (is_refetch_error, set_refetch_error) = signal(false);
+    let (source, set_source) = signal(None::<String>);
 
     // Get query client from context
     let client = use_context::<QueryClient>().expect("QueryClient not found in context");
     
-    // Create key signal
-    let key = Memo::new(move |_| key_fn());
+    // Create key signal, delayed by any debounce or throttle resolved for the new key
+    let key = delayed_key(Memo::new(move |_| key_fn()), {
+        let client = client.clone();
+        let options = options.clone();
+        move |key| KeyDelay::from_options(&client.resolve_query_options(key, options.clone()))
+    });
+    
+    // Ticket of a fetch still waiting for a scheduler slot
+    let pending_ticket = StoredValue::new(None::<u64>);
     
-    // Create fetch function for initial fetch
-    let initial_fetch = {
+    // Create fetch function
+    let run_fetch = {
         let client = client.clone();
-        let query_fn = query_fn.clone();
         let options = options.clone();
         
         move |force: bool| {
             let client = client.clone();
-            let query_fn = query_fn.clone();
+            let sources_for = sources_for.clone();
             let options = options.clone();
             
-            spawn_local(async move {
+            async move {
                 let current_key = key.get();
+                let options = client.resolve_query_options(&current_key, options);
+                let sources = sources_for(&options);
                 
                 // Check cache first
                 if let Some(cache_entry) = client.get_cache_entry(&current_key) {
@@ -135,6 +612,7 @@ where
                         // Use cached data
                         if let Ok(cached_data) = cache_entry.get_data::<T>() {
                             set_data.set(Some(cached_data));
+                            set_source.set(cache_entry.meta.source);
                             set_loading.set(false);
                             set_status.set(QueryStatus::Success);
                             return;
@@ -145,75 +623,60 @@ where
                 // Fetch new data
                 set_status.set(QueryStatus::Loading);
                 
-                let result = execute_with_retry(
-                    &query_fn,
-                    &options.retry,
-                ).await;
-                
-                match result {
-                    Ok(result_data) => {
-                        // Cache the data
-                        if let Ok(()) = client.set_query_data(&current_key, result_data.clone()) {
-                            set_data.set(Some(result_data));
-                            set_error.set(None);
-                            set_status.set(QueryStatus::Success);
-                        }
-                    }
-                    Err(err) => {
-                        set_error.set(Some(err.clone()));
-                        set_status.set(QueryStatus::Error);
-                    }
+                // Wait for a free fetch slot, superseding any fetch still queued
+                let ticket = client.scheduler().enqueue(&current_key, options.priority);
+                let ticket_id = ticket.id();
+                if let Some(Some(previous)) = pending_ticket.try_get_value() {
+                    client.scheduler().cancel(previous);
                 }
+                pending_ticket.set_value(Some(ticket_id));
                 
-                set_loading.set(false);
-            });
-        }
-    };
-    
-    // Create fetch function for refetch
-    let refetch_fn = {
-        let client = client.clone();
-        let query_fn = query_fn.clone();
-        let options = options.clone();
-        
-        move |force: bool| {
-            let client = client.clone();
-            let query_fn = query_fn.clone();
-            let options = options.clone();
-            
-            spawn_local(async move {
-                let current_key = key.get();
-                
-                // Check cache first
-                if let Some(cache_entry) = client.get_cache_entry(&current_key) {
-                    if !force && !cache_entry.is_stale() {
-                        // Use cached data
-                        if let Ok(cached_data) = cache_entry.get_data::<T>() {
-                            set_data.set(Some(cached_data));
-                            set_loading.set(false);
-                            set_status.set(QueryStatus::Success);
-                            return;
-                        }
+                let permit = ticket.wait().await;
+                pending_ticket.update_value(|pending| {
+                    if *pending == Some(ticket_id) {
+                        *pending = None;
                     }
-                }
+                });
                 
-                // Fetch new data
-                set_status.set(QueryStatus::Loading);
+                // A cancelled fetch leaves the state to whichever fetch replaced it
+                let Ok(_permit) = permit else { return };
                 
-                let result = execute_with_retry(
-                    &query_fn,
-                    &options.retry,
-                ).await;
+                // A hedge runs inside the same attempt and scheduler slot as the original request
+                let result = sources.fetch(&options).await;
                 
                 match result {
-                    Ok(result_data) => {
+                    Ok((result_data, result_source)) => {
                         // Cache the data
-                        if let Ok(()) = client.set_query_data(&current_key, result_data.clone()) {
+                        if let Ok(()) = client.set_query_result(&current_key, result_data.clone(), Some(result_source.clone()), &options) {
                             set_data.set(Some(result_data));
+                            set_source.set(Some(result_source));
                             set_error.set(None);
+                            set_refetch_error.set(false);
                             set_status.set(QueryStatus::Success);
                         }
                     }
+                    Err(err) if options.stale_if_error => {
+                        // Serve the last good data as long as it is young enough
+                        let stale_data = client
+                            .get_cache_entry(&current_key)
+                            .filter(|entry| entry.meta.is_usable_on_error(options.max_stale_if_error))
+                            .and_then(|entry| entry.get_data::<T>().ok());
+                        
+                        set_error.set(Some(err.clone()));
+                        match stale_data {
+                            Some(stale_data) => {
+                                client.set_query_error(&current_key, err);
+                                set_data.set(Some(stale_data));
+                                set_refetch_error.set(true);
+                                set_status.set(QueryStatus::Success);
+                            }
+                            None => {
+                                set_data.set(None);
+                                set_refetch_error.set(false);
+                                set_status.set(QueryStatus::Error);
+                            }
+                        }
+                    }
                     Err(err) => {
                         set_error.set(Some(err.clone()));
                         set_status.set(QueryStatus::Error);
@@ -221,14 +684,56 @@ where
                 }
                 
                 set_loading.set(false);
-            });
+            }
         }
     };
+    let fetch = {
+        let run_fetch = run_fetch.clone();
+        move |force: bool| spawn_local(run_fetch(force))
+    };
+    
+    // Let invalidation refetch this query and cache writes update it while it is mounted
+    let observer_id = options.enabled.then(|| {
+        client.register_observer(
+            key.get_untracked(),
+            move || run_fetch(true).boxed_local(),
+            move |entry| match entry.map(|entry| entry.get_data::<T>()) {
+                Some(Ok(cached_data)) => {
+                    set_data.set(Some(cached_data));
+                    set_source.set(entry.and_then(|entry| entry.meta.source.clone()));
+                    set_error.set(None);
+                    set_refetch_error.set(false);
+                    set_loading.set(false);
+                    set_status.set(QueryStatus::Success);
+                }
+                Some(Err(_)) => {}
+                None => set_data.set(None),
+            },
+        )
+    });
+    
+    // Cancel a queued fetch and stop observing when the query goes away
+    {
+        let client = client.clone();
+        let observer_id = observer_id.clone();
+        on_cleanup(move || {
+            if let Some(observer_id) = &observer_id {
+                client.unregister_observer(observer_id);
+            }
+            if let Some(Some(ticket_id)) = pending_ticket.try_get_value() {
+                client.scheduler().cancel(ticket_id);
+            }
+        });
+    }
     
     // Initial fetch
+    let initial_fetch = fetch.clone();
     Effect::new(move |_| {
         if options.enabled {
             let current_key = key.get();
+            if let Some(observer_id) = &observer_id {
+                client.update_observer_key(observer_id, current_key.clone());
+            }
             
             // Check cache first
             if let Some(cache_entry) = client.get_cache_entry(&current_key) {
@@ -236,6 +741,7 @@ where
                     // Use cached data
                     if let Ok(cached_data) = cache_entry.get_data::<T>() {
                         set_data.set(Some(cached_data));
+                        set_source.set(cache_entry.meta.source);
                         set_loading.set(false);
                         set_status.set(QueryStatus::Success);
                     }
@@ -262,7 +768,9 @@ where
         is_success: is_success.into(),
         is_error: is_error.into(),
         status: status.into(),
-        refetch: Callback::new(move |_| refetch_fn(true)),
+        is_refetch_error: is_refetch_error.into(),
+        source: source.into(),
+        refetch: Callback::new(move |_| fetch(true)),
     }
 }
 
@@ -275,10 +783,182 @@ mod tests {
         let options = QueryOptions::default()
             .with_stale_time(Duration::from_secs(60))
             .with_cache_time(Duration::from_secs(300))
+            .with_timeout(Duration::from_secs(5))
+            .with_max_stale_if_error(Duration::from_secs(600))
+            .with_hedge_after(Duration::from_millis(300))
+            .with_priority(FetchPriority::High)
+            .with_debounce(Duration::from_millis(250))
             .disabled();
         
         assert_eq!(options.stale_time, Duration::from_secs(60));
         assert_eq!(options.cache_time, Duration::from_secs(300));
+        assert_eq!(options.timeout, Some(Duration::from_secs(5)));
+        assert!(options.stale_if_error);
+        assert_eq!(options.max_stale_if_error, Some(Duration::from_secs(600)));
+        assert_eq!(options.hedge_after, Some(Duration::from_millis(300)));
+        assert_eq!(options.priority, FetchPriority::High);
+        assert_eq!(options.debounce, Some(Duration::from_millis(250)));
         assert!(!options.enabled);
     }
+    
+    #[test]
+    fn test_key_delay_waits() {
+        let now = Instant::now();
+        let options = QueryOptions::default().with_throttle(Duration::from_millis(100));
+        let throttle = KeyDelay::from_options(&options).unwrap();
+        
+        // A throttled key applies right away, then waits out the rest of the interval
+        assert_eq!(throttle.wait(now, None), Duration::ZERO);
+        assert_eq!(throttle.wait(now, Some(now - Duration::from_millis(30))), Duration::from_millis(70));
+        assert_eq!(throttle.wait(now, Some(now - Duration::from_millis(300))), Duration::ZERO);
+        
+        // A debounce always waits the full time and wins over a throttle
+        let debounce = KeyDelay::from_options(&options.with_debounce(Duration::from_millis(250))).unwrap();
+        assert_eq!(debounce, KeyDelay::Debounce(Duration::from_millis(250)));
+        assert_eq!(debounce.wait(now, Some(now)), Duration::from_millis(250));
+        
+        assert!(KeyDelay::from_options(&QueryOptions::default()).is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_sources_fall_back_in_order() {
+        let sources = QuerySources::new()
+            .with_source(
+                "primary",
+                || async { Err::<u32, _>(QueryError::NetworkError("primary down".to_string())) },
+                RetryConfig::new(0, Duration::from_millis(1)),
+            )
+            .with_source("replica", || async { Ok(42) }, RetryConfig::new(0, Duration::from_millis(1)))
+            .with_source("snapshot", || async { Ok(0) }, RetryConfig::new(0, Duration::from_millis(1)));
+        
+        let (data, source) = sources.fetch(&QueryOptions::default()).await.unwrap();
+        assert_eq!(data, 42);
+        assert_eq!(source, "replica");
+    }
+    
+    #[tokio::test]
+    async fn test_stale_if_error_keeps_invalidated_data() {
+        use crate::types::QueryKeyPattern;
+        
+        let _ = any_spawner::Executor::init_futures_executor();
+        let owner = Owner::new();
+        owner.set();
+        let client = QueryClient::new();
+        provide_context(client.clone());
+        let key = QueryKey::new(["users", "1"]);
+        client.set_query_data(&key, 7_u32).unwrap();
+        
+        let query = use_query(
+            {
+                let key = key.clone();
+                move || key.clone()
+            },
+            || async { Err::<u32, _>(QueryError::NetworkError("down".to_string())) },
+            QueryOptions::default()
+                .with_retry(RetryConfig::new(0, Duration::from_millis(1)))
+                .with_stale_if_error(),
+        );
+        
+        // Invalidation keeps the data around for the failed refetch to fall back on
+        client.invalidate_and_refetch_queries(&QueryKeyPattern::Prefix(QueryKey::from("users"))).await;
+        assert_eq!(query.data.get_untracked(), Some(7));
+        assert!(query.is_refetch_error.get_untracked());
+        assert!(client.get_cache_entry(&key).unwrap().is_stale());
+    }
+    
+    #[test]
+    fn test_key_change_uses_defaults_of_new_pattern() {
+        use crate::types::QueryKeyPattern;
+        
+        let _ = any_spawner::Executor::init_futures_executor();
+        let owner = Owner::new();
+        owner.set();
+        let client = QueryClient::new();
+        provide_context(client.clone());
+        client.set_query_defaults(
+            QueryKeyPattern::Prefix(QueryKey::from("todos")),
+            QueryDefaults::new().with_stale_time(Duration::from_secs(30)),
+        );
+        
+        let key = RwSignal::new(QueryKey::from("users"));
+        let _query = use_query(move || key.get(), || async { Ok(1_u32) }, QueryOptions::default());
+        any_spawner::Executor::poll_local();
+        assert_eq!(client.get_cache_entry(&QueryKey::from("users")).unwrap().meta.stale_time, QueryOptions::default().stale_time);
+        
+        // The new key is fetched with the defaults of the pattern it moved into
+        key.set(QueryKey::from("todos"));
+        any_spawner::Executor::poll_local();
+        assert_eq!(client.get_cache_entry(&QueryKey::from("todos")).unwrap().meta.stale_time, Duration::from_secs(30));
+    }
+    
+    #[test]
+    fn test_key_change_before_first_effect_run_is_applied() {
+        let _ = any_spawner::Executor::init_futures_executor();
+        let owner = Owner::new();
+        owner.set();
+        
+        let key = RwSignal::new(QueryKey::from("a"));
+        let applied = delayed_key(Memo::new(move |_| key.get()), |_| None);
+        key.set(QueryKey::from("b"));
+        any_spawner::Executor::poll_local();
+        assert_eq!(applied.get_untracked(), QueryKey::from("b"));
+    }
+    
+    #[tokio::test]
+    async fn test_debounced_key_supersedes_pending_key() {
+        let _ = any_spawner::Executor::init_futures_executor();
+        let owner = Owner::new();
+        owner.set();
+        
+        let key = RwSignal::new(QueryKey::from("a"));
+        let applied = delayed_key(Memo::new(move |_| key.get()), |_| Some(KeyDelay::Debounce(Duration::from_millis(200))));
+        let seen = StoredValue::new(Vec::new());
+        Effect::new(move |_| {
+            let key = applied.get();
+            seen.update_value(|seen| seen.push(key));
+        });
+        any_spawner::Executor::poll_local();
+        
+        key.set(QueryKey::from("b"));
+        any_spawner::Executor::poll_local();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        any_spawner::Executor::poll_local();
+        key.set(QueryKey::from("c"));
+        any_spawner::Executor::poll_local();
+        
+        // "b" was still waiting when "c" came in, so it is never applied
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        any_spawner::Executor::poll_local();
+        assert_eq!(seen.get_value(), vec![QueryKey::from("a"), QueryKey::from("c")]);
+    }
+    
+    #[tokio::test]
+    async fn test_previous_data_stays_while_key_is_debounced() {
+        let _ = any_spawner::Executor::init_futures_executor();
+        let owner = Owner::new();
+        owner.set();
+        provide_context(QueryClient::new());
+        
+        let key = RwSignal::new(QueryKey::new(["search", "ru"]));
+        let query = use_query(
+            move || key.get(),
+            move || {
+                let term = key.get_untracked().segments.last().cloned().unwrap_or_default();
+                async move { Ok(term.len()) }
+            },
+            QueryOptions::default().with_debounce(Duration::from_millis(100)),
+        );
+        any_spawner::Executor::poll_local();
+        assert_eq!(query.data.get_untracked(), Some(2));
+        
+        key.set(QueryKey::new(["search", "rust"]));
+        any_spawner::Executor::poll_local();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        any_spawner::Executor::poll_local();
+        assert_eq!(query.data.get_untracked(), Some(2));
+        
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        any_spawner::Executor::poll_local();
+        assert_eq!(query.data.get_untracked(), Some(4));
+    }
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 //! Retry logic and error handling for queries
 
+use std::any::Any;
 use std::time::Duration;
 use std::future::Future;
+use futures::future::{self, Either};
 use serde::{Serialize, Deserialize};
 
 /// Error types that can occur during query execution
@@ -37,7 +39,7 @@ impl std::fmt::Display for QueryError {
 impl std::error::Error for QueryError {}
 
 /// Configuration for retry behavior
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RetryConfig {
     /// Maximum number of retry attempts
     pub max_retries: usize,
@@ -112,22 +114,54 @@ where
     F: Fn() -> Fut + Clone,
     Fut: Future<Output = Result<T, QueryError>>,
 {
-    let mut last_error = None;
-    
+    execute_with_retry_and_timeout(query_fn, config, None).await
+}
+
+/// Execute a future with retry logic, failing any attempt that runs longer than `timeout`
+///
+/// A timed-out attempt is dropped and counts toward `max_retries` like any other
+/// `TimeoutError`. `QueryError`s are retried according to [`should_retry_error`];
+/// other error types are always retried.
+pub async fn execute_with_retry_and_timeout<F, Fut, T, E>(
+    query_fn: F,
+    config: &RetryConfig,
+    timeout: Option<Duration>,
+) -> Result<T, E>
+where
+    F: Fn() -> Fut + Clone,
+    Fut: Future<Output = Result<T, E>>,
+    E: From<QueryError> + 'static,
+{
+    execute_with_retry_if(query_fn, config, timeout, |error| is_retryable(error, config)).await
+}
+
+/// Execute a future with retry logic, retrying only the errors `should_retry` accepts
+///
+/// Behaves like [`execute_with_retry_and_timeout`], with `should_retry` replacing the
+/// built-in error classification.
+pub async fn execute_with_retry_if<F, Fut, T, E>(
+    query_fn: F,
+    config: &RetryConfig,
+    timeout: Option<Duration>,
+    should_retry: impl Fn(&E) -> bool,
+) -> Result<T, E>
+where
+    F: Fn() -> Fut + Clone,
+    Fut: Future<Output = Result<T, E>>,
+    E: From<QueryError> + 'static,
+{
     for attempt in 0..=config.max_retries {
-        match query_fn().await {
+        match with_timeout(query_fn(), timeout).await {
             Ok(result) => return Ok(result),
             Err(error) => {
-                last_error = Some(error.clone());
-                
                 // Check if we should retry this error
-                if !should_retry_error(&error, config) {
+                if !should_retry(&error) {
                     return Err(error);
                 }
                 
                 // Don't retry on the last attempt
                 if attempt == config.max_retries {
-                    break;
+                    return Err(error);
                 }
                 
                 // Calculate delay
@@ -139,7 +173,61 @@ where
         }
     }
     
-    Err(last_error.unwrap_or_else(|| QueryError::GenericError("Unknown error".to_string())))
+    Err(QueryError::GenericError("Unknown error".to_string()).into())
+}
+
+/// Run a future, dropping it with a `TimeoutError` once `timeout` elapses
+pub async fn with_timeout<Fut, T, E>(
+    future: Fut,
+    timeout: Option<Duration>,
+) -> Result<T, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: From<QueryError>,
+{
+    let Some(timeout) = timeout else {
+        return future.await;
+    };
+    
+    let future = std::pin::pin!(future);
+    let timer = std::pin::pin!(sleep(timeout));
+    
+    match future::select(future, timer).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(QueryError::TimeoutError(format!("Timed out after {:?}", timeout)).into()),
+    }
+}
+
+/// Run a request, sending an identical hedge request if the first one hasn't
+/// answered within `hedge_after`
+///
+/// The first successful response wins and the other request is dropped. If one
+/// of them fails, the result of the other one is used instead.
+pub async fn with_hedge<F, Fut, T, E>(
+    request_fn: F,
+    hedge_after: Option<Duration>,
+) -> Result<T, E>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let Some(hedge_after) = hedge_after else {
+        return request_fn().await;
+    };
+    
+    let primary = Box::pin(request_fn());
+    let timer = std::pin::pin!(sleep(hedge_after));
+    
+    let primary = match future::select(primary, timer).await {
+        Either::Left((result, _)) => return result,
+        Either::Right((_, primary)) => primary,
+    };
+    
+    let hedge = Box::pin(request_fn());
+    match future::select(primary, hedge).await {
+        Either::Left((Ok(data), _)) | Either::Right((Ok(data), _)) => Ok(data),
+        Either::Left((Err(_), other)) | Either::Right((Err(_), other)) => other.await,
+    }
 }
 
 /// Check if an error should be retried
@@ -153,6 +241,14 @@ pub fn should_retry_error(error: &QueryError, config: &RetryConfig) -> bool {
     }
 }
 
+/// Check if an error of any type should be retried
+pub(crate) fn is_retryable<E: 'static>(error: &E, config: &RetryConfig) -> bool {
+    match (error as &dyn Any).downcast_ref::<QueryError>() {
+        Some(error) => should_retry_error(error, config),
+        None => true,
+    }
+}
+
 /// Calculate delay for retry attempt
 fn calculate_delay(attempt: usize, config: &RetryConfig) -> Duration {
     if config.exponential_backoff {
@@ -165,7 +261,7 @@ fn calculate_delay(attempt: usize, config: &RetryConfig) -> Duration {
 }
 
 /// Sleep function that works in both native and WASM environments
-async fn sleep(duration: Duration) {
+pub(crate) async fn sleep(duration: Duration) {
     #[cfg(target_arch = "wasm32")]
     {
         let promise = js_sys::Promise::new(&mut |resolve, _| {
@@ -229,4 +325,78 @@ mod tests {
         assert_eq!(calculate_delay(1, &fixed_config), Duration::from_millis(100));
         assert_eq!(calculate_delay(2, &fixed_config), Duration::from_millis(100));
     }
+    
+    #[tokio::test]
+    async fn test_timeout_counts_toward_retries() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let config = RetryConfig::new(2, Duration::from_millis(1));
+        
+        let result: Result<(), QueryError> = execute_with_retry_and_timeout(
+            || {
+                let attempts = attempts.clone();
+                async move {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    sleep(Duration::from_secs(10)).await;
+                    Ok(())
+                }
+            },
+            &config,
+            Some(Duration::from_millis(10)),
+        ).await;
+        
+        assert!(matches!(result, Err(QueryError::TimeoutError(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_retry_with_custom_error_type() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        #[derive(Debug)]
+        struct ApiError;
+        
+        impl From<QueryError> for ApiError {
+            fn from(_: QueryError) -> Self {
+                ApiError
+            }
+        }
+        
+        let attempts = AtomicUsize::new(0);
+        let result: Result<(), ApiError> = execute_with_retry_and_timeout(
+            || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async { Err(ApiError) }
+            },
+            &RetryConfig::new(2, Duration::from_millis(1)),
+            None,
+        ).await;
+        
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_hedge_takes_first_response() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        let calls = AtomicUsize::new(0);
+        let result: Result<usize, QueryError> = with_hedge(
+            || {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    // The first request hangs, the hedge answers quickly
+                    let delay = if call == 0 { 10_000 } else { 5 };
+                    sleep(Duration::from_millis(delay)).await;
+                    Ok(call)
+                }
+            },
+            Some(Duration::from_millis(10)),
+        ).await;
+        
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,246 @@
+//! Fetch scheduling
+//!
+//! Limits how many fetches run at the same time and hands out free slots to
+//! queued fetches in priority order.
+
+use crate::retry::QueryError;
+use crate::types::QueryKey;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Priority of a fetch waiting for a free slot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum FetchPriority {
+    /// Fetches the user is actively waiting on
+    High,
+    /// Regular fetches
+    #[default]
+    Normal,
+    /// Fetches that can wait behind regular traffic
+    Low,
+    /// Prefetches and other speculative work
+    Idle,
+}
+
+/// A fetch waiting in the scheduler queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedFetch {
+    /// Ticket ID, usable with [`FetchScheduler::cancel`]
+    pub id: u64,
+    /// Query key of the fetch
+    pub key: QueryKey,
+    /// Priority of the fetch
+    pub priority: FetchPriority,
+    /// When the fetch was queued
+    #[serde(with = "crate::types::instant_serde")]
+    pub queued_at: Instant,
+}
+
+struct Waiter {
+    fetch: QueuedFetch,
+    sender: oneshot::Sender<FetchPermit>,
+}
+
+struct SchedulerState {
+    max_concurrent: Option<usize>,
+    running: usize,
+    next_id: u64,
+    queue: Vec<Waiter>,
+}
+
+/// Scheduler that bounds the number of concurrent fetches
+#[derive(Clone)]
+pub struct FetchScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+impl FetchScheduler {
+    /// Create a new scheduler; `None` means no limit
+    pub fn new(max_concurrent: Option<usize>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SchedulerState {
+                max_concurrent,
+                running: 0,
+                next_id: 0,
+                queue: Vec::new(),
+            })),
+        }
+    }
+
+    /// Get the maximum number of concurrent fetches
+    pub fn max_concurrent(&self) -> Option<usize> {
+        self.state.lock().max_concurrent
+    }
+
+    /// Change the maximum number of concurrent fetches
+    pub fn set_max_concurrent(&self, max_concurrent: Option<usize>) {
+        let mut state = self.state.lock();
+        state.max_concurrent = max_concurrent;
+        self.dispatch(&mut state);
+    }
+
+    /// Queue a fetch and get a ticket that resolves once a slot is free
+    pub fn enqueue(&self, key: &QueryKey, priority: FetchPriority) -> FetchTicket {
+        let (sender, receiver) = oneshot::channel();
+        let mut state = self.state.lock();
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.queue.push(Waiter {
+            fetch: QueuedFetch {
+                id,
+                key: key.clone(),
+                priority,
+                queued_at: Instant::now(),
+            },
+            sender,
+        });
+        self.dispatch(&mut state);
+
+        FetchTicket { id, receiver }
+    }
+
+    /// Wait for a free slot
+    pub async fn acquire(&self, key: &QueryKey, priority: FetchPriority) -> Result<FetchPermit, QueryError> {
+        self.enqueue(key, priority).wait().await
+    }
+
+    /// Cancel a queued fetch; returns false if it already started
+    pub fn cancel(&self, id: u64) -> bool {
+        let mut state = self.state.lock();
+        let before = state.queue.len();
+        state.queue.retain(|waiter| waiter.fetch.id != id);
+        state.queue.len() != before
+    }
+
+    /// Cancel all queued fetches for a key
+    pub fn cancel_key(&self, key: &QueryKey) -> usize {
+        let mut state = self.state.lock();
+        let before = state.queue.len();
+        state.queue.retain(|waiter| waiter.fetch.key != *key);
+        before - state.queue.len()
+    }
+
+    /// Get the queued fetches in the order they will start
+    pub fn queued(&self) -> Vec<QueuedFetch> {
+        let state = self.state.lock();
+        let mut queued: Vec<QueuedFetch> = state.queue.iter().map(|waiter| waiter.fetch.clone()).collect();
+        queued.sort_by_key(|fetch| (fetch.priority, fetch.id));
+        queued
+    }
+
+    /// Get the number of queued fetches
+    pub fn queued_count(&self) -> usize {
+        self.state.lock().queue.len()
+    }
+
+    /// Get the number of running fetches
+    pub fn running_count(&self) -> usize {
+        self.state.lock().running
+    }
+
+    /// Hand out free slots to the highest priority waiters
+    fn dispatch(&self, state: &mut SchedulerState) {
+        while state.max_concurrent.map_or(true, |max| state.running < max) {
+            let next = state
+                .queue
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, waiter)| (waiter.fetch.priority, waiter.fetch.id))
+                .map(|(index, _)| index);
+
+            let Some(index) = next else { break };
+            let waiter = state.queue.remove(index);
+            let permit = FetchPermit {
+                state: Some(self.state.clone()),
+            };
+
+            match waiter.sender.send(permit) {
+                Ok(()) => state.running += 1,
+                // The waiter went away; don't let the permit release a slot it never took
+                Err(mut permit) => permit.state = None,
+            }
+        }
+    }
+}
+
+impl Default for FetchScheduler {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Handle for a queued fetch
+pub struct FetchTicket {
+    id: u64,
+    receiver: oneshot::Receiver<FetchPermit>,
+}
+
+impl FetchTicket {
+    /// Get the ticket ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Wait until the fetch may start
+    pub async fn wait(self) -> Result<FetchPermit, QueryError> {
+        self.receiver
+            .await
+            .map_err(|_| QueryError::GenericError("Fetch cancelled".to_string()))
+    }
+}
+
+/// A running fetch slot, released when dropped
+pub struct FetchPermit {
+    state: Option<Arc<Mutex<SchedulerState>>>,
+}
+
+impl Drop for FetchPermit {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            let scheduler = FetchScheduler { state };
+            let mut guard = scheduler.state.lock();
+            guard.running -= 1;
+            scheduler.dispatch(&mut guard);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrency_limit_and_priority() {
+        let scheduler = FetchScheduler::new(Some(1));
+        let running = scheduler.acquire(&QueryKey::from("first"), FetchPriority::Normal).await.unwrap();
+
+        let low = scheduler.enqueue(&QueryKey::from("low"), FetchPriority::Low);
+        let high = scheduler.enqueue(&QueryKey::from("high"), FetchPriority::High);
+        assert_eq!(scheduler.running_count(), 1);
+        assert_eq!(scheduler.queued()[0].key, QueryKey::from("high"));
+
+        // Releasing the slot starts the high priority fetch first
+        drop(running);
+        let permit = high.wait().await.unwrap();
+        assert_eq!(scheduler.queued_count(), 1);
+
+        drop(permit);
+        assert!(low.wait().await.is_ok());
+        assert_eq!(scheduler.running_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_fetch() {
+        let scheduler = FetchScheduler::new(Some(1));
+        let _running = scheduler.acquire(&QueryKey::from("first"), FetchPriority::Normal).await.unwrap();
+
+        let ticket = scheduler.enqueue(&QueryKey::from("second"), FetchPriority::Normal);
+        assert!(scheduler.cancel(ticket.id()));
+        assert!(ticket.wait().await.is_err());
+        assert_eq!(scheduler.queued_count(), 0);
+    }
+}
@@ -8,7 +8,11 @@ use crate::types::QueryKey;
 use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
+use futures::channel::oneshot;
+use parking_lot::{Mutex, RwLock};
 
 #[cfg(feature = "sync")]
 use leptos_sync_core::{
@@ -25,6 +29,94 @@ pub enum NetworkStatus {
     Offline,
 }
 
+/// Listener notified when the network status changes
+type NetworkListener = Arc<dyn Fn(NetworkStatus) + Send + Sync>;
+
+/// Tracks whether the app is online and wakes up work waiting for the network
+#[derive(Clone)]
+pub struct OnlineManager {
+    status: Arc<RwLock<NetworkStatus>>,
+    waiters: Arc<Mutex<Vec<oneshot::Sender<()>>>>,
+    listeners: Arc<RwLock<HashMap<u64, NetworkListener>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl OnlineManager {
+    /// Create a new online manager that starts online
+    pub fn new() -> Self {
+        Self {
+            status: Arc::new(RwLock::new(NetworkStatus::Online)),
+            waiters: Arc::new(Mutex::new(Vec::new())),
+            listeners: Arc::new(RwLock::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Get the current network status
+    pub fn status(&self) -> NetworkStatus {
+        *self.status.read()
+    }
+
+    /// Check if the app is online
+    pub fn is_online(&self) -> bool {
+        self.status() == NetworkStatus::Online
+    }
+
+    /// Change the network status, notifying listeners if it changed
+    pub fn set_status(&self, status: NetworkStatus) {
+        {
+            let mut current = self.status.write();
+            if *current == status {
+                return;
+            }
+            *current = status;
+        }
+
+        if status == NetworkStatus::Online {
+            for waiter in self.waiters.lock().drain(..) {
+                let _ = waiter.send(());
+            }
+        }
+
+        let listeners: Vec<NetworkListener> = self.listeners.read().values().cloned().collect();
+        for listener in listeners {
+            listener(status);
+        }
+    }
+
+    /// Wait until the app is online
+    pub async fn wait_online(&self) {
+        let receiver = {
+            let mut waiters = self.waiters.lock();
+            if self.is_online() {
+                return;
+            }
+            let (sender, receiver) = oneshot::channel();
+            waiters.push(sender);
+            receiver
+        };
+        let _ = receiver.await;
+    }
+
+    /// Call a listener whenever the network status changes
+    pub fn subscribe(&self, listener: impl Fn(NetworkStatus) + Send + Sync + 'static) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.listeners.write().insert(id, Arc::new(listener));
+        id
+    }
+
+    /// Remove a listener
+    pub fn unsubscribe(&self, id: u64) {
+        self.listeners.write().remove(&id);
+    }
+}
+
+impl Default for OnlineManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Conflict resolution strategies
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConflictResolutionStrategy {
@@ -4,6 +4,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use serde::{Serialize, Deserialize};
 use std::fmt;
+use crate::retry::QueryError;
 
 /// Query status enum
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -57,6 +58,17 @@ impl QueryKey {
         self
     }
     
+    /// Get the key with every segment equal to `from` replaced by `to`, if it has one
+    pub fn replace_segment(&self, from: &str, to: &str) -> Option<Self> {
+        self.segments.iter().any(|segment| segment == from).then(|| Self {
+            segments: self
+                .segments
+                .iter()
+                .map(|segment| if segment == from { to.to_string() } else { segment.clone() })
+                .collect(),
+        })
+    }
+    
     /// Get the segments as a slice
     pub fn segments(&self) -> &[String] {
         &self.segments
@@ -155,6 +167,16 @@ impl Default for QueryObserverId {
     }
 }
 
+/// Kind of data held by a cache entry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheEntryKind {
+    /// Data of a regular query
+    #[default]
+    Query,
+    /// Pages and page params of an infinite query
+    Infinite,
+}
+
 /// Metadata about a query
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryMeta {
@@ -165,13 +187,21 @@ pub struct QueryMeta {
     pub stale_time: Duration,
     #[serde(with = "duration_serde")]
     pub cache_time: Duration,
+    /// Error of the last failed refetch, while the data above is still being served
+    pub error: Option<QueryError>,
+    /// Name of the source that served the data
+    pub source: Option<String>,
+    /// Kind of data the entry holds
+    pub kind: CacheEntryKind,
+    /// Set when the query is invalidated, until its data is replaced
+    pub invalidated: bool,
 }
 
 impl QueryMeta {
     /// Check if the query is stale
     pub fn is_stale(&self) -> bool {
         let age = Instant::now().duration_since(self.updated_at);
-        age > self.stale_time
+        self.invalidated || age > self.stale_time
     }
     
     /// Check if the query has expired
@@ -179,6 +209,12 @@ impl QueryMeta {
         let age = Instant::now().duration_since(self.updated_at);
         age > self.cache_time
     }
+    
+    /// Check if the data is still young enough to serve after a failed refetch
+    pub fn is_usable_on_error(&self, max_stale: Option<Duration>) -> bool {
+        let age = Instant::now().duration_since(self.updated_at);
+        max_stale.map_or(true, |max_stale| age <= max_stale)
+    }
 }
 
 impl Default for QueryMeta {
@@ -188,12 +224,16 @@ impl Default for QueryMeta {
             updated_at: Instant::now(),
             stale_time: Duration::from_secs(0),
             cache_time: Duration::from_secs(5 * 60), // 5 minutes
+            error: None,
+            source: None,
+            kind: CacheEntryKind::default(),
+            invalidated: false,
         }
     }
 }
 
 /// Serialization helpers for Instant
-mod instant_serde {
+pub(crate) mod instant_serde {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
     use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
@@ -281,5 +321,10 @@ mod tests {
         // Should be stale after waiting
         meta.updated_at = Instant::now() - Duration::from_secs(120);
         assert!(meta.is_stale());
+        
+        // Invalidated data is stale however young it is
+        meta.updated_at = Instant::now();
+        meta.invalidated = true;
+        assert!(meta.is_stale());
     }
 }
@@ -53,10 +53,31 @@ mod tests {
         assert_eq!(options.cache_time, Duration::from_secs(300));
     }
 
+    #[test]
+    fn test_query_options_struct_update() {
+        // Options built from a struct literal still take client defaults for fields they leave alone
+        let options = QueryOptions {
+            cache_time: Duration::from_secs(600),
+            ..Default::default()
+        };
+        let defaults = QueryDefaults::new()
+            .with_stale_time(Duration::from_secs(30))
+            .with_cache_time(Duration::from_secs(60))
+            .with_hedge_after(Duration::from_millis(200))
+            .with_max_stale_if_error(Duration::from_secs(120));
+        let options = defaults.apply_to(options);
+        
+        assert_eq!(options.stale_time, Duration::from_secs(30));
+        assert_eq!(options.cache_time, Duration::from_secs(600));
+        assert_eq!(options.hedge_after, Some(Duration::from_millis(200)));
+        assert!(options.stale_if_error);
+        assert_eq!(options.max_stale_if_error, Some(Duration::from_secs(120)));
+    }
+    
     #[test]
     fn test_mutation_options_builder() {
         // Test the documented MutationOptions usage
-        let options = MutationOptions::default()
+        let options: MutationOptions = MutationOptions::default()
             .with_retry(RetryConfig::new(3, Duration::from_millis(100)));
         
         assert_eq!(options.retry.max_retries, 3);
@@ -170,14 +191,14 @@ mod tests {
         // Test exact invalidation
         let exact_pattern = QueryKeyPattern::Exact(key1.clone());
         client.invalidate_queries(&exact_pattern);
-        assert!(client.get_cache_entry(&key1).is_none());
-        assert!(client.get_cache_entry(&key2).is_some());
-        assert!(client.get_cache_entry(&key3).is_some());
+        assert!(client.get_cache_entry(&key1).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key2).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key3).unwrap().meta.invalidated);
         
         // Test prefix invalidation
         let prefix_pattern = QueryKeyPattern::Prefix(QueryKey::new(&["users"]));
         client.invalidate_queries(&prefix_pattern);
-        assert!(client.get_cache_entry(&key2).is_none());
-        assert!(client.get_cache_entry(&key3).is_some());
+        assert!(client.get_cache_entry(&key2).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key3).unwrap().meta.invalidated);
     }
 }
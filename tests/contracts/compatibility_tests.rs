@@ -230,7 +230,7 @@ mod tests {
         // Test that mutations work consistently
         let client = QueryClient::new();
         // Test that we can create mutation options
-        let options = MutationOptions::default();
+        let options: MutationOptions = MutationOptions::default();
         
         // Test that the options are valid
         assert!(options.retry.max_retries >= 0);
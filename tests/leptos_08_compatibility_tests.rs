@@ -62,7 +62,7 @@ mod tests {
         // Test that use_mutation works with Leptos 0.8 patterns
         // Note: This won't run without a Leptos runtime, but it tests compilation
         let _mutation_fn = |id: u32| async move { mock_fetch_user(id).await };
-        let _options = MutationOptions::default();
+        let _options: MutationOptions = MutationOptions::default();
         
         assert!(true);
     }
@@ -93,7 +93,7 @@ mod tests {
         
         // Test that we can use callbacks in our library
         let _mutation_fn = |id: u32| async move { mock_fetch_user(id).await };
-        let _options = MutationOptions::default();
+        let _options: MutationOptions = MutationOptions::default();
         
         assert!(true);
     }
@@ -225,7 +225,7 @@ mod tests {
         // Test cache invalidation
         let pattern = QueryKeyPattern::Prefix(QueryKey::new(&["cache"]));
         client.invalidate_queries(&pattern);
-        assert!(client.get_cache_entry(&key).is_none());
+        assert!(client.get_cache_entry(&key).unwrap().meta.invalidated);
     }
 
     #[test]
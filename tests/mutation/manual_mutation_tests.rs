@@ -105,14 +105,14 @@ mod tests {
         let prefix_pattern = QueryKeyPattern::Prefix(QueryKey::new(&["users"]));
         client.invalidate_queries(&prefix_pattern);
         
-        // Users entries should be removed, posts entry should remain
-        assert!(client.get_cache_entry(&key1).is_none());
-        assert!(client.get_cache_entry(&key2).is_none());
-        assert!(client.get_cache_entry(&key3).is_some());
+        // Users entries should be invalidated, posts entry should remain valid
+        assert!(client.get_cache_entry(&key1).unwrap().meta.invalidated);
+        assert!(client.get_cache_entry(&key2).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key3).unwrap().meta.invalidated);
         
         // This would fail if we had a mutation that inverted the invalidation logic:
-        // assert!(client.get_cache_entry(&key1).is_some()); // This would fail!
-        // assert!(client.get_cache_entry(&key3).is_none()); // This would fail!
+        // assert!(!client.get_cache_entry(&key1).unwrap().meta.invalidated); // This would fail!
+        // assert!(client.get_cache_entry(&key3).unwrap().meta.invalidated); // This would fail!
     }
 
     #[test]
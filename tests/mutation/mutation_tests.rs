@@ -99,15 +99,15 @@ mod tests {
         // Test exact invalidation
         let exact_pattern = QueryKeyPattern::Exact(key1.clone());
         client.invalidate_queries(&exact_pattern);
-        assert!(client.get_cache_entry(&key1).is_none());
-        assert!(client.get_cache_entry(&key2).is_some());
-        assert!(client.get_cache_entry(&key3).is_some());
+        assert!(client.get_cache_entry(&key1).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key2).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key3).unwrap().meta.invalidated);
         
         // Test prefix invalidation
         let prefix_pattern = QueryKeyPattern::Prefix(QueryKey::new(&["users"]));
         client.invalidate_queries(&prefix_pattern);
-        assert!(client.get_cache_entry(&key2).is_none());
-        assert!(client.get_cache_entry(&key3).is_some());
+        assert!(client.get_cache_entry(&key2).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&key3).unwrap().meta.invalidated);
     }
 
     #[test]
@@ -177,11 +177,11 @@ mod tests {
             // Invalidate with pattern
             client.invalidate_queries(&pattern);
             
-            // Check that matching keys are removed
+            // Check that matching keys are invalidated
             for key in &keys {
-                let should_exist = !key.matches_pattern(&pattern);
-                let exists = client.get_cache_entry(key).is_some();
-                prop_assert_eq!(exists, should_exist);
+                let should_be_invalidated = key.matches_pattern(&pattern);
+                let invalidated = client.get_cache_entry(key).unwrap().meta.invalidated;
+                prop_assert_eq!(invalidated, should_be_invalidated);
             }
         }
 
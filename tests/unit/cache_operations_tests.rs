@@ -108,10 +108,10 @@ mod tests {
         let pattern = QueryKeyPattern::Exact(user1_key.clone());
         client.invalidate_queries(&pattern);
         
-        // Only user1 should be removed
-        assert!(client.get_cache_entry(&user1_key).is_none());
-        assert!(client.get_cache_entry(&user2_key).is_some());
-        assert!(client.get_cache_entry(&post1_key).is_some());
+        // Only user1 should be invalidated
+        assert!(client.get_cache_entry(&user1_key).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&user2_key).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&post1_key).unwrap().meta.invalidated);
     }
 
     #[test]
@@ -146,15 +146,15 @@ mod tests {
         let pattern = QueryKeyPattern::Prefix(QueryKey::new(&["users"]));
         client.invalidate_queries(&pattern);
         
-        // All users-related queries should be removed
-        assert!(client.get_cache_entry(&users_key).is_none());
-        assert!(client.get_cache_entry(&user1_key).is_none());
-        assert!(client.get_cache_entry(&user2_key).is_none());
-        assert!(client.get_cache_entry(&user1_posts_key).is_none());
+        // All users-related queries should be invalidated
+        assert!(client.get_cache_entry(&users_key).unwrap().meta.invalidated);
+        assert!(client.get_cache_entry(&user1_key).unwrap().meta.invalidated);
+        assert!(client.get_cache_entry(&user2_key).unwrap().meta.invalidated);
+        assert!(client.get_cache_entry(&user1_posts_key).unwrap().meta.invalidated);
         
-        // Posts queries should remain
-        assert!(client.get_cache_entry(&posts_key).is_some());
-        assert!(client.get_cache_entry(&post1_key).is_some());
+        // Posts queries should remain valid
+        assert!(!client.get_cache_entry(&posts_key).unwrap().meta.invalidated);
+        assert!(!client.get_cache_entry(&post1_key).unwrap().meta.invalidated);
     }
 
     #[test]
@@ -179,15 +179,15 @@ mod tests {
         let pattern = QueryKeyPattern::Contains("1".to_string());
         client.invalidate_queries(&pattern);
         
-        // Queries containing "1" should be removed
-        assert!(client.get_cache_entry(&user1_key).is_none());
-        assert!(client.get_cache_entry(&user1_posts_key).is_none());
-        assert!(client.get_cache_entry(&post1_key).is_none());
+        // Queries containing "1" should be invalidated
+        assert!(client.get_cache_entry(&user1_key).unwrap().meta.invalidated);
+        assert!(client.get_cache_entry(&user1_posts_key).unwrap().meta.invalidated);
+        assert!(client.get_cache_entry(&post1_key).unwrap().meta.invalidated);
         
-        // Queries not containing "1" should remain
-        assert!(client.get_cache_entry(&user2_key).is_some());
-        // Note: comment1_key contains "1" so it should be removed
-        assert!(client.get_cache_entry(&comment1_key).is_none());
+        // Queries not containing "1" should remain valid
+        assert!(!client.get_cache_entry(&user2_key).unwrap().meta.invalidated);
+        // Note: comment1_key contains "1" so it should be invalidated
+        assert!(client.get_cache_entry(&comment1_key).unwrap().meta.invalidated);
     }
 
     #[test]